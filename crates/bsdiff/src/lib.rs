@@ -0,0 +1,480 @@
+//! Binary diff and patch, after Colin Percival's bsdiff 4.3 ("Naive
+//! differences of executable code", 2003).
+//!
+//! A patch turns `old` into `new` with a list of controls. Each control
+//! adds `add` bytes of the diff stream to as many bytes of `old` (mod 256),
+//! copies `copy` bytes of the extra stream as is, then moves the position
+//! in `old` by `seek`. Approximate matches make the diff stream mostly
+//! zeros, so patches compress well, but compressing them is left to the
+//! caller.
+//!
+//! Patch format, all integers little-endian:
+//!
+//! | offset | size   | field                                          |
+//! |--------|--------|------------------------------------------------|
+//! | 0      | 8      | magic, `b"BSDIFFSA"`                           |
+//! | 8      | 8      | number of controls                             |
+//! | 16     | 8      | diff stream length                             |
+//! | 24     | 8      | extra stream length                            |
+//! | 32     | 8      | length of `new`                                |
+//! | 40     | 24 * n | controls: `add` u64, `copy` u64, `seek` i64    |
+//! |        |        | the diff stream, then the extra stream         |
+
+use std::{
+    convert::TryFrom,
+    fmt,
+    io::{self, Read, Write},
+};
+
+pub const MAGIC: &[u8; 8] = b"BSDIFFSA";
+pub const HEADER_LEN: usize = 40;
+
+/// One step of a patch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Control {
+    /// Bytes of the diff stream to add to `old`
+    pub add: u64,
+    /// Bytes of the extra stream to copy
+    pub copy: u64,
+    /// Move in `old` afterwards
+    pub seek: i64,
+}
+
+/// A patch, see the crate documentation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub new_len: u64,
+    pub controls: Vec<Control>,
+    pub diff: Vec<u8>,
+    pub extra: Vec<u8>,
+}
+
+/// Error returned when reading or applying a patch
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Not a patch
+    BadMagic,
+    /// The patch is truncated, or inconsistent with itself or `old`
+    Corrupt,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::BadMagic => write!(f, "not a patch"),
+            Error::Corrupt => write!(f, "corrupt patch"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::Corrupt,
+            _ => Error::Io(e),
+        }
+    }
+}
+
+/// Computes a patch from `old` to `new`
+pub fn diff(old: &[u8], new: &[u8]) -> Patch {
+    let sa = divsufsort::sort(old);
+    let search = |needle: &[u8]| -> (usize, usize) {
+        if old.is_empty() {
+            return (0, 0);
+        }
+        let lcs = sa.longest_substring_match(needle);
+        (lcs.start(), lcs.len())
+    };
+
+    let mut patch = Patch {
+        new_len: new.len() as u64,
+        controls: Vec::new(),
+        diff: Vec::new(),
+        extra: Vec::new(),
+    };
+
+    let (oldsize, newsize) = (old.len() as isize, new.len() as isize);
+    let old_at = |i: isize| old[i as usize];
+    let new_at = |i: isize| new[i as usize];
+
+    let mut scan = 0_isize;
+    let mut len = 0_isize;
+    let mut pos = 0_isize;
+    let mut lastscan = 0_isize;
+    let mut lastpos = 0_isize;
+    let mut lastoffset = 0_isize;
+
+    while scan < newsize {
+        // Find the next exact match that's long enough compared to how
+        // well `old` lines up with `new` at the previous offset.
+        let mut oldscore = 0_isize;
+        scan += len;
+        let mut scsc = scan;
+        while scan < newsize {
+            let (p, l) = search(&new[scan as usize..]);
+            pos = p as isize;
+            len = l as isize;
+
+            while scsc < scan + len {
+                if scsc + lastoffset < oldsize && old_at(scsc + lastoffset) == new_at(scsc) {
+                    oldscore += 1;
+                }
+                scsc += 1;
+            }
+            if (len == oldscore && len != 0) || len > oldscore + 8 {
+                break;
+            }
+            if scan + lastoffset < oldsize && old_at(scan + lastoffset) == new_at(scan) {
+                oldscore -= 1;
+            }
+            scan += 1;
+        }
+
+        if len == oldscore && scan != newsize {
+            continue;
+        }
+
+        // Extend the previous match forwards, and this one backwards,
+        // as long as at least half of the bytes match.
+        let (mut s, mut best, mut lenf) = (0, 0, 0);
+        let mut i = 0;
+        while lastscan + i < scan && lastpos + i < oldsize {
+            if old_at(lastpos + i) == new_at(lastscan + i) {
+                s += 1;
+            }
+            i += 1;
+            if s * 2 - i > best * 2 - lenf {
+                best = s;
+                lenf = i;
+            }
+        }
+
+        let mut lenb = 0;
+        if scan < newsize {
+            let (mut s, mut best) = (0, 0);
+            let mut i = 1;
+            while scan >= lastscan + i && pos >= i {
+                if old_at(pos - i) == new_at(scan - i) {
+                    s += 1;
+                }
+                if s * 2 - i > best * 2 - lenb {
+                    best = s;
+                    lenb = i;
+                }
+                i += 1;
+            }
+        }
+
+        // If the extensions overlap, split them where it's best.
+        if lastscan + lenf > scan - lenb {
+            let overlap = (lastscan + lenf) - (scan - lenb);
+            let (mut s, mut best, mut lens) = (0, 0, 0);
+            for i in 0..overlap {
+                if new_at(lastscan + lenf - overlap + i) == old_at(lastpos + lenf - overlap + i) {
+                    s += 1;
+                }
+                if new_at(scan - lenb + i) == old_at(pos - lenb + i) {
+                    s -= 1;
+                }
+                if s > best {
+                    best = s;
+                    lens = i + 1;
+                }
+            }
+            lenf += lens - overlap;
+            lenb -= lens;
+        }
+
+        for i in 0..lenf {
+            patch
+                .diff
+                .push(new_at(lastscan + i).wrapping_sub(old_at(lastpos + i)));
+        }
+        let copy = (scan - lenb) - (lastscan + lenf);
+        for i in 0..copy {
+            patch.extra.push(new_at(lastscan + lenf + i));
+        }
+        patch.controls.push(Control {
+            add: lenf as u64,
+            copy: copy as u64,
+            seek: ((pos - lenb) - (lastpos + lenf)) as i64,
+        });
+
+        lastscan = scan - lenb;
+        lastpos = pos - lenb;
+        lastoffset = pos - scan;
+    }
+    patch
+}
+
+/// Applies `patch` to `old`
+pub fn apply(old: &[u8], patch: &Patch) -> Result<Vec<u8>, Error> {
+    let new_len = usize::try_from(patch.new_len).map_err(|_| Error::Corrupt)?;
+    let mut new = Vec::with_capacity(new_len.min(old.len() + patch.diff.len() + patch.extra.len()));
+    let (mut diff, mut extra) = (&patch.diff[..], &patch.extra[..]);
+    let mut oldpos = 0_i64;
+
+    for control in &patch.controls {
+        let add = usize::try_from(control.add).map_err(|_| Error::Corrupt)?;
+        let copy = usize::try_from(control.copy).map_err(|_| Error::Corrupt)?;
+        if add > diff.len() || copy > extra.len() || new.len() + add + copy > new_len {
+            return Err(Error::Corrupt);
+        }
+
+        let end = oldpos.checked_add(add as i64).ok_or(Error::Corrupt)?;
+        for (&d, pos) in diff[..add].iter().zip(oldpos..end) {
+            // bytes outside of `old` count as zeros
+            let x = usize::try_from(pos)
+                .ok()
+                .and_then(|i| old.get(i))
+                .copied()
+                .unwrap_or(0);
+            new.push(d.wrapping_add(x));
+        }
+        oldpos = end;
+        diff = &diff[add..];
+        new.extend_from_slice(&extra[..copy]);
+        extra = &extra[copy..];
+        oldpos = oldpos.checked_add(control.seek).ok_or(Error::Corrupt)?;
+    }
+
+    if new.len() != new_len || !diff.is_empty() || !extra.is_empty() {
+        return Err(Error::Corrupt);
+    }
+    Ok(new)
+}
+
+impl Patch {
+    /// Writes the patch to `w`, see the crate documentation for the format
+    pub fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let mut header = [0; HEADER_LEN];
+        header[0..8].copy_from_slice(MAGIC);
+        header[8..16].copy_from_slice(&(self.controls.len() as u64).to_le_bytes());
+        header[16..24].copy_from_slice(&(self.diff.len() as u64).to_le_bytes());
+        header[24..32].copy_from_slice(&(self.extra.len() as u64).to_le_bytes());
+        header[32..40].copy_from_slice(&self.new_len.to_le_bytes());
+        w.write_all(&header)?;
+
+        for c in &self.controls {
+            w.write_all(&c.add.to_le_bytes())?;
+            w.write_all(&c.copy.to_le_bytes())?;
+            w.write_all(&c.seek.to_le_bytes())?;
+        }
+        w.write_all(&self.diff)?;
+        w.write_all(&self.extra)?;
+        w.flush()
+    }
+
+    /// Reads a patch written by `write`
+    pub fn read<R: Read>(mut r: R) -> Result<Self, Error> {
+        let mut header = [0; HEADER_LEN];
+        r.read_exact(&mut header)?;
+        if &header[0..8] != MAGIC {
+            return Err(Error::BadMagic);
+        }
+        let u64_at = |i: usize| {
+            let mut bytes = [0; 8];
+            bytes.copy_from_slice(&header[i..i + 8]);
+            u64::from_le_bytes(bytes)
+        };
+        let (controls, diff, extra, new_len) = (u64_at(8), u64_at(16), u64_at(24), u64_at(32));
+
+        // Lengths aren't trusted for allocations: a truncated stream
+        // is only found out by reading it.
+        let mut read_vec = |len: u64| -> Result<Vec<u8>, Error> {
+            let mut buf = Vec::new();
+            (&mut r).take(len).read_to_end(&mut buf)?;
+            if buf.len() as u64 != len {
+                return Err(Error::Corrupt);
+            }
+            Ok(buf)
+        };
+
+        let raw = read_vec(controls.checked_mul(24).ok_or(Error::Corrupt)?)?;
+        let controls = raw
+            .chunks(24)
+            .map(|c| {
+                let mut word = [[0; 8]; 3];
+                for (w, bytes) in word.iter_mut().zip(c.chunks(8)) {
+                    w.copy_from_slice(bytes);
+                }
+                Control {
+                    add: u64::from_le_bytes(word[0]),
+                    copy: u64::from_le_bytes(word[1]),
+                    seek: i64::from_le_bytes(word[2]),
+                }
+            })
+            .collect();
+        let diff = read_vec(diff)?;
+        let extra = read_vec(extra)?;
+        if r.read(&mut [0])? != 0 {
+            return Err(Error::Corrupt);
+        }
+
+        Ok(Self {
+            new_len,
+            controls,
+            diff,
+            extra,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.write(&mut buf).unwrap();
+        buf
+    }
+}
+
+/// Applies a patch in serialized form to `old`
+pub fn patch(old: &[u8], patch: &[u8]) -> Result<Vec<u8>, Error> {
+    apply(old, &Patch::read(patch)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(old: &[u8], new: &[u8]) -> Patch {
+        let p = diff(old, new);
+        assert_eq!(apply(old, &p).unwrap(), new);
+        let bytes = p.to_bytes();
+        assert_eq!(Patch::read(&bytes[..]).unwrap(), p);
+        assert_eq!(patch(old, &bytes).unwrap(), new);
+        p
+    }
+
+    #[test]
+    fn small() {
+        let texts: &[&[u8]] = &[
+            b"",
+            b"a",
+            b"banana",
+            b"bananas",
+            b"ananas and bananas",
+            b"xyz",
+        ];
+        for old in texts {
+            for new in texts {
+                round_trip(old, new);
+            }
+        }
+    }
+
+    #[test]
+    fn testdata() {
+        let files: &[&[u8]] = &[
+            include_bytes!(
+                "../../divsufsort/src/testdata/crash-04dc74e45e66386a3312a5a5825b020bcadc175c"
+            ),
+            include_bytes!(
+                "../../divsufsort/src/testdata/crash-16356e91966a827f79e49167170194fc3088a7ab"
+            ),
+            include_bytes!(
+                "../../divsufsort/src/testdata/crash-4f8c31dec8c3678a07e0fbacc6bd69e7cc9037fb"
+            ),
+            include_bytes!(
+                "../../divsufsort/src/testdata/crash-8765ef2258178ca027876eab83e01d6d58db9ca0"
+            ),
+            include_bytes!(
+                "../../divsufsort/src/testdata/crash-90b42d1c55ee90a8b004fb9db1853429ceb4c4ba"
+            ),
+            include_bytes!(
+                "../../divsufsort/src/testdata/crash-c792e788de61771b6cd65c1aa5670c62e57a33c4"
+            ),
+            include_bytes!(
+                "../../divsufsort/src/testdata/crash-ce407adf7cf638d3fa89b5637a94355d7d658872"
+            ),
+            include_bytes!(
+                "../../divsufsort/src/testdata/crash-cf8673530fdca659e0ddf070b4718b9c0bb504ec"
+            ),
+            include_bytes!("../../divsufsort/src/testdata/fuzz1"),
+            include_bytes!("../../divsufsort/src/testdata/fuzz2"),
+            include_bytes!("../../divsufsort/src/testdata/fuzz3"),
+        ];
+        for old in files {
+            for new in files {
+                round_trip(old, new);
+            }
+
+            // a few local edits, against the unchanged file
+            let n = old.len();
+            let mut new = old.to_vec();
+            new.insert(n / 4, 0x42);
+            new.drain(n / 2..n / 2 + 10.min(n / 8));
+            new[3 * n / 4] ^= 0xff;
+            new.extend_from_slice(b"trailer");
+            let p = round_trip(old, &new);
+            assert!(p.extra.len() < 32, "extra: {} bytes", p.extra.len());
+            assert!(p.diff.iter().filter(|&&d| d != 0).count() < 32);
+        }
+    }
+
+    #[test]
+    fn format() {
+        let p = Patch {
+            new_len: 5,
+            controls: vec![Control {
+                add: 2,
+                copy: 3,
+                seek: -1,
+            }],
+            diff: vec![0, 1],
+            extra: b"xyz".to_vec(),
+        };
+        let mut expected = b"BSDIFFSA".to_vec();
+        for x in &[1_u64, 2, 3, 5, 2, 3] {
+            expected.extend_from_slice(&x.to_le_bytes());
+        }
+        expected.extend_from_slice(&[0xff; 8]);
+        expected.extend_from_slice(b"\x00\x01xyz");
+        assert_eq!(p.to_bytes(), expected);
+        assert_eq!(patch(b"ab", &expected).unwrap(), b"acxyz");
+
+        assert!(matches!(patch(b"ab", b"BSDIFF40"), Err(Error::Corrupt)));
+        assert!(matches!(patch(b"ab", &[0; 64]), Err(Error::BadMagic)));
+        for len in 0..expected.len() {
+            assert!(patch(b"ab", &expected[..len]).is_err());
+        }
+        let mut long = expected.clone();
+        long.push(0);
+        assert!(matches!(patch(b"ab", &long), Err(Error::Corrupt)));
+        // the controls don't add up to `new_len`
+        let mut short = p;
+        short.new_len = 4;
+        assert!(matches!(apply(b"ab", &short), Err(Error::Corrupt)));
+
+        // seeking as far as possible, then reading on
+        let far = Patch {
+            new_len: 2,
+            controls: vec![
+                Control {
+                    add: 0,
+                    copy: 0,
+                    seek: i64::MAX,
+                },
+                Control {
+                    add: 2,
+                    copy: 0,
+                    seek: 0,
+                },
+            ],
+            diff: vec![0, 0],
+            extra: vec![],
+        };
+        assert!(matches!(apply(b"ab", &far), Err(Error::Corrupt)));
+        assert!(matches!(patch(b"ab", &far.to_bytes()), Err(Error::Corrupt)));
+    }
+}
@@ -8,16 +8,28 @@ fn main() {
         "debug" => {
             // muffin
         }
-        "release" => {
-            if build.get_compiler().is_like_msvc() {
-                build.flag("/Oi").flag("/Ot").flag("/Ox").flag("/Oy");
-            }
+        "release" if build.get_compiler().is_like_msvc() => {
+            build.flag("/Oi").flag("/Ot").flag("/Ox").flag("/Oy");
         }
         _ => {}
     };
     if std::env::var("CARGO_FEATURE_CROSSCHECK").is_ok() {
         build.flag("-DENABLE_CROSSCHECK=1");
     }
+    if std::env::var("CARGO_FEATURE_OPENMP").is_ok() {
+        // sorts type B* buckets in parallel, see `sort_typeBstar`
+        let compiler = build.get_compiler();
+        if compiler.is_like_msvc() {
+            // the runtime (vcomp) is pulled in by the compiler
+            build.flag("/openmp");
+        } else if compiler.is_like_clang() {
+            build.flag("-fopenmp");
+            println!("cargo:rustc-link-lib=omp");
+        } else {
+            build.flag("-fopenmp");
+            println!("cargo:rustc-link-lib=gomp");
+        }
+    }
 
     build
         .file("c-sources/divsufsort.c")
@@ -25,5 +37,13 @@ fn main() {
         .file("c-sources/trsort.c")
         .file("c-sources/utils.c");
 
+    // Same sources, with 64-bit `saidx_t` and `64`-suffixed symbols
+    let mut build64 = build.clone();
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+    build64
+        .define("BUILD_DIVSUFSORT64", None)
+        .out_dir(out_dir.join("64"));
+
     build.compile("libdivsufsort.a");
+    build64.compile("libdivsufsort64.a");
 }
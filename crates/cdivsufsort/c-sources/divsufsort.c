@@ -28,9 +28,35 @@
 #ifdef _OPENMP
 # include <omp.h>
 #endif
+#ifdef ENABLE_CROSSCHECK
+# include <stdarg.h>
+#endif
 
 /*- Cross-checking -*/
-FILE *CROSSCHECK_FILE;
+#ifdef ENABLE_CROSSCHECK
+CROSSCHECK_THREAD_LOCAL crosscheck_sink_t CROSSCHECK_SINK;
+
+/* Formats a part of a record and hands it to the sink. */
+void
+crosscheck_printf(const char *format, ...) {
+  char buf[256], *p = buf;
+  va_list ap;
+  int len;
+
+  va_start(ap, format);
+  len = vsnprintf(buf, sizeof(buf), format, ap);
+  va_end(ap);
+  if(len < 0) { return; }
+  if((size_t)len >= sizeof(buf)) {
+    if((p = (char *)malloc((size_t)len + 1)) == NULL) { return; }
+    va_start(ap, format);
+    vsnprintf(p, (size_t)len + 1, format, ap);
+    va_end(ap);
+  }
+  CROSSCHECK_SINK.write(CROSSCHECK_SINK.ctx, p, (size_t)len);
+  if(p != buf) { free(p); }
+}
+#endif
 
 /*- Private Functions -*/
 
@@ -124,6 +150,36 @@ note:
 
 
     /* Sort the type B* substrings using sssort. */
+#ifdef _OPENMP
+    tmp = omp_get_max_threads();
+    buf = SA + m, bufsize = (n - (2 * m)) / tmp;
+    c0 = ALPHABET_SIZE - 2, c1 = ALPHABET_SIZE - 1, j = m;
+#pragma omp parallel default(shared) private(curbuf, k, l, d0, d1, tmp)
+    {
+      tmp = omp_get_thread_num();
+      curbuf = buf + tmp * bufsize;
+      k = 0;
+      for(;;) {
+        #pragma omp critical(sssort_lock)
+        {
+          if(0 < (l = j)) {
+            d0 = c0, d1 = c1;
+            do {
+              k = BUCKET_BSTAR(d0, d1);
+              if(--d1 <= d0) {
+                d1 = ALPHABET_SIZE - 1;
+                if(--d0 < 0) { break; }
+              }
+            } while(((l - k) <= 1) && (0 < (l = k)));
+            c0 = d0, c1 = d1, j = k;
+          }
+        }
+        if(l == 0) { break; }
+        sssort(T, PAb, SA + k, SA + l,
+               curbuf, bufsize, 2, n, *(SA + k) == (m - 1));
+      }
+    }
+#else
     buf = SA + m, bufsize = n - (2 * m);
     for(c0 = ALPHABET_SIZE - 2, j = m; 0 < j; --c0) {
       for(c1 = ALPHABET_SIZE - 1; c0 < c1; j = i, --c1) {
@@ -136,6 +192,7 @@ note:
         }
       }
     }
+#endif
 
     /* Compute ranks of type B* substrings. */
     for(i = m - 1; 0 <= i; --i) {
@@ -330,14 +387,6 @@ construct_BWT(const sauchar_t *T, saidx_t *SA,
 
 saint_t
 divsufsort(const sauchar_t *T, saidx_t *SA, saidx_t n) {
-#ifdef ENABLE_CROSSCHECK
-  CROSSCHECK_FILE = fopen("crosscheck/c", "wb");
-  if (!CROSSCHECK_FILE) {
-    fprintf(stderr, "Could not open crosscheck file");
-    return -2;
-  }
-#endif
-
   saidx_t *bucket_A, *bucket_B;
   saidx_t m;
   saint_t err = 0;
@@ -362,12 +411,23 @@ divsufsort(const sauchar_t *T, saidx_t *SA, saidx_t n) {
   free(bucket_B);
   free(bucket_A);
 
+  return err;
+}
+
 #ifdef ENABLE_CROSSCHECK
-  fclose(CROSSCHECK_FILE);
-#endif
+saint_t
+divsufsort_with_trace(const sauchar_t *T, saidx_t *SA, saidx_t n,
+                      crosscheck_write_t write, void *ctx) {
+  crosscheck_sink_t saved = CROSSCHECK_SINK;
+  saint_t err;
+
+  CROSSCHECK_SINK.write = write, CROSSCHECK_SINK.ctx = ctx;
+  err = divsufsort(T, SA, n);
+  CROSSCHECK_SINK = saved;
 
   return err;
 }
+#endif
 
 saidx_t
 divbwt(const sauchar_t *T, sauchar_t *U, saidx_t *A, saidx_t n) {
@@ -32,6 +32,7 @@ extern "C" {
 #endif /* __cplusplus */
 
 #include <inttypes.h>
+#include <stddef.h>
 
 #ifndef DIVSUFSORT_API
 # ifdef DIVSUFSORT_BUILD_DLL
@@ -75,6 +76,27 @@ DIVSUFSORT_API
 saint_t
 divsufsort(const sauchar_t *T, saidx_t *SA, saidx_t n);
 
+#ifndef CROSSCHECK_WRITE_T
+#define CROSSCHECK_WRITE_T
+/* Receives `len` bytes of crosscheck trace. */
+typedef void (*crosscheck_write_t)(void *ctx, const char *data, size_t len);
+#endif /* CROSSCHECK_WRITE_T */
+
+/**
+ * Same as divsufsort, but writes a crosscheck trace of the sort to `write`,
+ * on the calling thread only. Only built with ENABLE_CROSSCHECK.
+ * @param T[0..n-1] The input string.
+ * @param SA[0..n-1] The output array of suffixes.
+ * @param n The length of the given string.
+ * @param write The trace sink, called with `ctx`.
+ * @param ctx Passed as is to `write`.
+ * @return 0 if no error occurred, -1 or -2 otherwise.
+ */
+DIVSUFSORT_API
+saint_t
+divsufsort_with_trace(const sauchar_t *T, saidx_t *SA, saidx_t n,
+                      crosscheck_write_t write, void *ctx);
+
 /**
  * Constructs the burrows-wheeler transformed string of a given string.
  * @param T[0..n-1] The input string.
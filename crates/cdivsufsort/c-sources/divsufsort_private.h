@@ -79,6 +79,10 @@ extern "C" {
 # define sa_simplesearch sa_simplesearch64
 # define sssort sssort64
 # define trsort trsort64
+# define dss_flush dss_flush64
+# define divsufsort_with_trace divsufsort64_with_trace
+# define CROSSCHECK_SINK CROSSCHECK_SINK64
+# define crosscheck_printf crosscheck_printf64
 #else
 # include "divsufsort.h"
 #endif
@@ -146,62 +150,66 @@ extern "C" {
 
 #ifdef ENABLE_CROSSCHECK
 
-extern FILE *CROSSCHECK_FILE;
+/* Records are tab-separated lines, see the `crosscheck` module of the
+   Rust port for the format. The phase is the source file's name.
+   They go to the calling thread's sink, set by divsufsort_with_trace,
+   and are dropped when it has none. */
+#if defined(_MSC_VER)
+# define CROSSCHECK_THREAD_LOCAL __declspec(thread)
+#else
+# define CROSSCHECK_THREAD_LOCAL __thread
+#endif
+
+typedef struct {
+  crosscheck_write_t write;
+  void *ctx;
+} crosscheck_sink_t;
+
+extern CROSSCHECK_THREAD_LOCAL crosscheck_sink_t CROSSCHECK_SINK;
+
+void
+crosscheck_printf(const char *format, ...);
+
+static void
+crosscheck_begin(const char *file, const char *kind) {
+  const char *base = file, *p, *dot = NULL;
+  for(p = file; *p; p++) {
+    if((*p == '/') || (*p == '\\')) { base = p + 1; dot = NULL; }
+    else if(*p == '.') { dot = p; }
+  }
+  if(dot == NULL) { dot = p; }
+  crosscheck_printf("%.*s\t%s\t", (int)(dot - base), base, kind);
+}
+
 #define crosscheck(...) \
     do { \
-      fprintf(CROSSCHECK_FILE, __VA_ARGS__); \
-      fprintf(CROSSCHECK_FILE, "\n"); \
+      if (CROSSCHECK_SINK.write != NULL) { \
+        crosscheck_begin(__FILE__, "msg"); \
+        crosscheck_printf(__VA_ARGS__); \
+        crosscheck_printf("\n"); \
+      } \
     } while (0)
 
-#define SA_dump(SA, start, len, label) \
+#define crosscheck_values(kind, label, len, value) \
   do { \
-    fprintf(CROSSCHECK_FILE, ":: %s\n", label); \
-    for (int z = 0; z < len; z++) { \
-      fprintf(CROSSCHECK_FILE, "%d ", SA[start+z]); \
-      if ((z+1)%25==0) { \
-        fprintf(CROSSCHECK_FILE, "\n"); \
-      } \
+    if (CROSSCHECK_SINK.write == NULL) { break; } \
+    crosscheck_begin(__FILE__, kind); \
+    crosscheck_printf("%s\t", label); \
+    for (long long z = 0; z < (long long)(len); z++) { \
+      crosscheck_printf(z ? " %lld" : "%lld", (long long)(value)); \
     } \
-    fprintf(CROSSCHECK_FILE, "\n"); \
-  } while (0);
+    crosscheck_printf("\n"); \
+  } while (0)
 
-// #define SA_dump(SA, label) \
-//   do { \
-//     fprintf(CROSSCHECK_FILE, ":: %s\n", label); \
-//     fprintf(CROSSCHECK_FILE, "SA = ["); \
-//     for (int z = 0; z < n; z++) { \
-//       if (z == n - 1) { \
-//         fprintf(CROSSCHECK_FILE, "%d", SA[z]); \
-//       } else { \
-//         fprintf(CROSSCHECK_FILE, "%d, ", SA[z]); \
-//       } \
-//     } \
-//     fprintf(CROSSCHECK_FILE, "]\n"); \
-//   } while (0);
+#define SA_dump(SA, start, len, label) \
+  crosscheck_values("sa", label, len, SA[start+z]);
 
 #define A_dump(A, label) \
-  do { \
-    fprintf(CROSSCHECK_FILE, ":: %s\n", label); \
-    fprintf(CROSSCHECK_FILE, "A = ["); \
-    for (int z = 0; z < BUCKET_A_SIZE; z++) { \
-      if (z == BUCKET_A_SIZE - 1) { \
-        fprintf(CROSSCHECK_FILE, "%d", BUCKET_A(z)); \
-      } else { \
-        fprintf(CROSSCHECK_FILE, "%d, ", BUCKET_A(z)); \
-      } \
-    } \
-    fprintf(CROSSCHECK_FILE, "]\n"); \
-  } while (0);
+  crosscheck_values("a", label, BUCKET_A_SIZE, BUCKET_A(z))
 
 #define BSTAR_dump(label) \
-  do { \
-    crosscheck("%s B* dump:", label); \
-    for (int ii = 0; ii < ALPHABET_SIZE; ii++) { \
-      for (int jj = 0; jj < ALPHABET_SIZE; jj++) { \
-        crosscheck("%s B*[%d,%d]=%d", label, ii, jj, BUCKET_BSTAR(ii, jj)); \
-      } \
-    } \
-  } while (0);
+  crosscheck_values("bstar", label, ALPHABET_SIZE * ALPHABET_SIZE, \
+                    BUCKET_BSTAR(z / ALPHABET_SIZE, z % ALPHABET_SIZE))
 
 #else
 
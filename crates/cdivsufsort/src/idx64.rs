@@ -0,0 +1,81 @@
+//! Bindings for `divsufsort64`, the same C sources built with a 64-bit
+//! `saidx_t`, for inputs of 2 GiB or more.
+
+extern "C" {
+    fn divsufsort64(T: *const u8, SA: *mut i64, n: i64) -> i32;
+}
+
+/// Sort suffixes of `text` and store their lexographic order
+/// in the given suffix array `sa`.
+/// Will panic if `sa.len()` != `text.len()`
+pub fn sort_in_place(text: &[u8], sa: &mut [i64]) {
+    assert_eq!(
+        text.len(),
+        sa.len(),
+        "text and suffix array should have same len"
+    );
+    assert!(
+        text.len() < i64::MAX as usize,
+        "text too large, should not exceed {} bytes",
+        i64::MAX - 1
+    );
+
+    let ret = unsafe { divsufsort64(text.as_ptr(), sa.as_mut_ptr(), text.len() as i64) };
+    assert_eq!(0, ret);
+}
+
+/// Sort suffixes
+pub fn sort(text: &[u8]) -> sacabase::SuffixArray<'_, i64> {
+    let mut sa = vec![0; text.len()];
+    sort_in_place(text, &mut sa);
+    sacabase::SuffixArray::new(text, sa)
+}
+
+/// Same as `sort`, but writes a crosscheck trace of the sort to `trace`,
+/// see `cdivsufsort::sort_with_trace`
+#[cfg(feature = "crosscheck")]
+pub fn sort_with_trace<'a, W: std::io::Write>(
+    text: &'a [u8],
+    trace: &mut W,
+) -> std::io::Result<sacabase::SuffixArray<'a, i64>> {
+    use crate::trace;
+
+    assert!(
+        text.len() < i64::MAX as usize,
+        "text too large, should not exceed {} bytes",
+        i64::MAX - 1
+    );
+    let mut sa = vec![0; text.len()];
+    let mut sink = trace::Sink::new(trace);
+    let ret = unsafe {
+        trace::divsufsort64_with_trace(
+            text.as_ptr(),
+            sa.as_mut_ptr(),
+            text.len() as i64,
+            trace::write,
+            sink.ctx(),
+        )
+    };
+    assert_eq!(0, ret);
+    sink.finish()?;
+    Ok(sacabase::SuffixArray::new(text, sa))
+}
+
+/// The 64-bit build of libdivsufsort, as a `sacabase::Saca`
+pub struct CDivSufSort;
+
+impl sacabase::Saca for CDivSufSort {
+    type Index = i64;
+
+    fn name(&self) -> &'static str {
+        "c-divsufsort64"
+    }
+
+    fn max_len(&self) -> usize {
+        i64::MAX as usize - 1
+    }
+
+    fn sort_in_place(&self, text: &[u8], sa: &mut [i64]) {
+        sort_in_place(text, sa)
+    }
+}
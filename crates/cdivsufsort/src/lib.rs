@@ -1,8 +1,170 @@
+use std::{ffi::CStr, fmt, ops::Range, ptr};
+
+pub mod idx64;
+
+mod ffi {
+    use std::os::raw::c_char;
+
+    extern "C" {
+        pub fn divsufsort(T: *const u8, SA: *mut i32, n: i32) -> i32;
+        pub fn divbwt(T: *const u8, U: *mut u8, A: *mut i32, n: i32) -> i32;
+        pub fn divsufsort_version() -> *const c_char;
+        pub fn bw_transform(T: *const u8, U: *mut u8, SA: *mut i32, n: i32, idx: *mut i32) -> i32;
+        pub fn inverse_bw_transform(T: *const u8, U: *mut u8, A: *mut i32, n: i32, idx: i32)
+            -> i32;
+        pub fn sufcheck(T: *const u8, SA: *const i32, n: i32, verbose: i32) -> i32;
+        pub fn sa_search(
+            T: *const u8,
+            Tsize: i32,
+            P: *const u8,
+            Psize: i32,
+            SA: *const i32,
+            SAsize: i32,
+            left: *mut i32,
+        ) -> i32;
+        pub fn sa_simplesearch(
+            T: *const u8,
+            Tsize: i32,
+            SA: *const i32,
+            SAsize: i32,
+            c: i32,
+            left: *mut i32,
+        ) -> i32;
+    }
+}
+
 extern "C" {
-    fn divsufsort(T: *const u8, SA: *mut i32, n: i32) -> i32;
     pub fn dss_flush();
 }
 
+#[cfg(feature = "crosscheck")]
+mod trace {
+    use std::{
+        io::{self, Write},
+        os::raw::{c_char, c_void},
+        slice,
+    };
+
+    /// `crosscheck_write_t`, see `divsufsort.h`
+    pub type WriteFn = extern "C" fn(ctx: *mut c_void, data: *const c_char, len: usize);
+
+    extern "C" {
+        pub fn divsufsort_with_trace(
+            T: *const u8,
+            SA: *mut i32,
+            n: i32,
+            write: WriteFn,
+            ctx: *mut c_void,
+        ) -> i32;
+        pub fn divsufsort64_with_trace(
+            T: *const u8,
+            SA: *mut i64,
+            n: i64,
+            write: WriteFn,
+            ctx: *mut c_void,
+        ) -> i32;
+    }
+
+    /// Where the C side's trace goes, and the first error writing it
+    pub struct Sink<'a> {
+        out: &'a mut dyn Write,
+        error: Option<io::Error>,
+    }
+
+    impl<'a> Sink<'a> {
+        pub fn new(out: &'a mut dyn Write) -> Self {
+            Self { out, error: None }
+        }
+
+        pub fn ctx(&mut self) -> *mut c_void {
+            self as *mut Self as *mut c_void
+        }
+
+        pub fn finish(self) -> io::Result<()> {
+            match self.error {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        }
+    }
+
+    /// Called by the C side, with a `Sink` as `ctx`
+    pub extern "C" fn write(ctx: *mut c_void, data: *const c_char, len: usize) {
+        let sink = unsafe { &mut *(ctx as *mut Sink) };
+        if sink.error.is_none() {
+            let data = unsafe { slice::from_raw_parts(data as *const u8, len) };
+            if let Err(e) = sink.out.write_all(data) {
+                sink.error = Some(e);
+            }
+        }
+    }
+}
+
+/// Error returned by the libdivsufsort wrappers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Two slices that should have the same length don't
+    LengthMismatch,
+    /// Input is too large for 32-bit indices
+    TooLarge,
+    /// libdivsufsort rejected the arguments (-1)
+    InvalidArguments,
+    /// libdivsufsort could not allocate memory (-2)
+    OutOfMemory,
+    /// `sufcheck`: an entry of the suffix array is out of range
+    OutOfRange,
+    /// `sufcheck`: suffixes are not sorted by their first character
+    WrongOrder,
+    /// `sufcheck`: a suffix is in the wrong position
+    WrongPosition,
+    /// Not the Burrows-Wheeler transform of any text, with that
+    /// primary index
+    InvalidBwt,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Error::LengthMismatch => "slices should have the same length",
+            Error::TooLarge => "input too large for 32-bit indices",
+            Error::InvalidArguments => "invalid arguments",
+            Error::OutOfMemory => "out of memory",
+            Error::OutOfRange => "suffix array entry out of range",
+            Error::WrongOrder => "suffixes in wrong order",
+            Error::WrongPosition => "suffix in wrong position",
+            Error::InvalidBwt => "invalid Burrows-Wheeler transform",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Maps the -1/-2 error codes shared by most libdivsufsort functions
+fn check(ret: i32) -> Result<i32, Error> {
+    match ret {
+        -1 => Err(Error::InvalidArguments),
+        -2 => Err(Error::OutOfMemory),
+        _ => Ok(ret),
+    }
+}
+
+/// Returns `len` as a C index, if it fits
+fn c_len(len: usize) -> Result<i32, Error> {
+    if len < i32::MAX as usize {
+        Ok(len as i32)
+    } else {
+        Err(Error::TooLarge)
+    }
+}
+
+/// Returns the version of libdivsufsort
+pub fn version() -> &'static str {
+    unsafe { CStr::from_ptr(ffi::divsufsort_version()) }
+        .to_str()
+        .unwrap()
+}
+
 /// Sort suffixes of `text` and store their lexographic order
 /// in the given suffix array `sa`.
 /// Will panic if `sa.len()` != `text.len()`
@@ -13,18 +175,338 @@ pub fn sort_in_place(text: &[u8], sa: &mut [i32]) {
         "text and suffix array should have same len"
     );
     assert!(
-        text.len() < i32::max_value() as usize,
+        text.len() < i32::MAX as usize,
         "text too large, should not exceed {} bytes",
-        i32::max_value() - 1
+        i32::MAX - 1
     );
 
-    let ret = unsafe { divsufsort(text.as_ptr(), sa.as_mut_ptr(), text.len() as i32) };
+    let ret = unsafe { ffi::divsufsort(text.as_ptr(), sa.as_mut_ptr(), text.len() as i32) };
     assert_eq!(0, ret);
 }
 
-//// Sort suffixes
-pub fn sort<'a>(text: &'a [u8]) -> sacabase::SuffixArray<i32> {
+/// Sort suffixes
+pub fn sort(text: &[u8]) -> sacabase::SuffixArray<'_, i32> {
     let mut sa = vec![0; text.len()];
     sort_in_place(text, &mut sa);
     sacabase::SuffixArray::new(text, sa)
 }
+
+/// Same as `sort`, but writes a crosscheck trace of the sort to `trace`,
+/// in the format of `divsufsort::crosscheck`. Only the calling thread's
+/// work is traced, so traces of `openmp` builds are incomplete.
+#[cfg(feature = "crosscheck")]
+pub fn sort_with_trace<'a, W: std::io::Write>(
+    text: &'a [u8],
+    trace: &mut W,
+) -> std::io::Result<sacabase::SuffixArray<'a, i32>> {
+    assert!(
+        text.len() < i32::MAX as usize,
+        "text too large, should not exceed {} bytes",
+        i32::MAX - 1
+    );
+    let mut sa = vec![0; text.len()];
+    let mut sink = trace::Sink::new(trace);
+    let ret = unsafe {
+        trace::divsufsort_with_trace(
+            text.as_ptr(),
+            sa.as_mut_ptr(),
+            text.len() as i32,
+            trace::write,
+            sink.ctx(),
+        )
+    };
+    assert_eq!(0, ret);
+    sink.finish()?;
+    Ok(sacabase::SuffixArray::new(text, sa))
+}
+
+/// libdivsufsort, as a `sacabase::Saca`
+pub struct CDivSufSort;
+
+impl sacabase::Saca for CDivSufSort {
+    type Index = i32;
+
+    fn name(&self) -> &'static str {
+        "c-divsufsort"
+    }
+
+    fn max_len(&self) -> usize {
+        i32::MAX as usize - 1
+    }
+
+    fn sort_in_place(&self, text: &[u8], sa: &mut [i32]) {
+        sort_in_place(text, sa)
+    }
+}
+
+/// Burrows-Wheeler transform of `text`, using `divbwt`.
+/// Returns the transformed text and its primary index.
+pub fn bwt(text: &[u8]) -> Result<(Vec<u8>, i32), Error> {
+    let n = c_len(text.len())?;
+    let mut bwt = vec![0; text.len()];
+    let pidx = check(unsafe { ffi::divbwt(text.as_ptr(), bwt.as_mut_ptr(), ptr::null_mut(), n) })?;
+    Ok((bwt, pidx))
+}
+
+/// Burrows-Wheeler transform of `text`, using its suffix array `sa`.
+/// Returns the transformed text and its primary index, or the error
+/// of `check_sa` if `sa` isn't the suffix array of `text`.
+pub fn bw_transform(text: &[u8], sa: &[i32]) -> Result<(Vec<u8>, i32), Error> {
+    // the C version trusts `sa` with its indices
+    check_sa(text, sa, false)?;
+    let n = c_len(text.len())?;
+    let mut bwt = vec![0; text.len()];
+    let mut pidx = 0;
+    // `SA` is only written to when `T == U`, which is never the case here
+    check(unsafe {
+        ffi::bw_transform(
+            text.as_ptr(),
+            bwt.as_mut_ptr(),
+            sa.as_ptr() as *mut i32,
+            n,
+            &mut pidx,
+        )
+    })?;
+    Ok((bwt, pidx))
+}
+
+/// Inverse Burrows-Wheeler transform, given the transformed text and
+/// the primary index returned by `bwt` or `bw_transform`.
+pub fn inverse_bwt(bwt: &[u8], pidx: i32) -> Result<Vec<u8>, Error> {
+    let mut aux = vec![0; bwt.len()];
+    inverse_bwt_with_aux(bwt, pidx, &mut aux)
+}
+
+/// Same as `inverse_bwt`, but uses `aux` (which should have the same
+/// length as `bwt`) as scratch space instead of allocating it.
+pub fn inverse_bwt_with_aux(bwt: &[u8], pidx: i32, aux: &mut [i32]) -> Result<Vec<u8>, Error> {
+    if bwt.len() != aux.len() {
+        return Err(Error::LengthMismatch);
+    }
+    let n = c_len(bwt.len())?;
+    check_bwt(bwt, pidx, aux)?;
+    // the C version leaves single-byte outputs untouched,
+    // so start from a copy of the input.
+    let mut text = bwt.to_vec();
+    check(unsafe {
+        ffi::inverse_bw_transform(bwt.as_ptr(), text.as_mut_ptr(), aux.as_mut_ptr(), n, pidx)
+    })?;
+    Ok(text)
+}
+
+/// Checks that the walk `inverse_bw_transform` does, from `pidx`, visits
+/// every position before coming back to the start: it doesn't, and reads
+/// out of bounds, when `bwt` and `pidx` don't go together. Builds the same
+/// table in `aux`, which the C version overwrites.
+fn check_bwt(bwt: &[u8], pidx: i32, aux: &mut [i32]) -> Result<(), Error> {
+    let n = bwt.len();
+    if n <= 1 || pidx <= 0 || pidx as usize > n {
+        // handled (or rejected) by the C version
+        return Ok(());
+    }
+    let idx = pidx as usize;
+
+    let mut next = [0; 256];
+    for &c in bwt {
+        next[c as usize] += 1;
+    }
+    let mut sum = 0;
+    for x in next.iter_mut() {
+        sum += *x;
+        *x = sum - *x;
+    }
+    for (i, &c) in bwt.iter().enumerate() {
+        aux[next[c as usize]] = if i < idx { i } else { i + 1 } as i32;
+        next[c as usize] += 1;
+    }
+
+    let mut p = idx;
+    for _ in 1..n {
+        p = aux[p - 1] as usize;
+        if p == 0 {
+            return Err(Error::InvalidBwt);
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `sa` is the suffix array of `text`, using `sufcheck`.
+/// When `verbose` is set, libdivsufsort reports details on stderr.
+pub fn check_sa(text: &[u8], sa: &[i32], verbose: bool) -> Result<(), Error> {
+    if text.len() != sa.len() {
+        return Err(Error::LengthMismatch);
+    }
+    let n = c_len(text.len())?;
+    match unsafe { ffi::sufcheck(text.as_ptr(), sa.as_ptr(), n, verbose as i32) } {
+        0 => Ok(()),
+        -1 => Err(Error::InvalidArguments),
+        -2 => Err(Error::OutOfRange),
+        -3 => Err(Error::WrongOrder),
+        _ => Err(Error::WrongPosition),
+    }
+}
+
+/// Returns the interval of `sa` whose suffixes start with `pattern`,
+/// using `sa_search`. `sa` is checked with `check_sa` first, in O(n),
+/// since the C version trusts it with its indices.
+pub fn search(text: &[u8], sa: &[i32], pattern: &[u8]) -> Result<Range<usize>, Error> {
+    check_sa(text, sa, false)?;
+    let mut left = 0;
+    let count = check(unsafe {
+        ffi::sa_search(
+            text.as_ptr(),
+            c_len(text.len())?,
+            pattern.as_ptr(),
+            c_len(pattern.len())?,
+            sa.as_ptr(),
+            c_len(sa.len())?,
+            &mut left,
+        )
+    })?;
+    let left = left.max(0) as usize;
+    Ok(left..left + count as usize)
+}
+
+/// Returns the interval of `sa` whose suffixes start with byte `c`,
+/// using `sa_simplesearch`. `sa` is checked like in `search`.
+pub fn simple_search(text: &[u8], sa: &[i32], c: u8) -> Result<Range<usize>, Error> {
+    check_sa(text, sa, false)?;
+    let mut left = 0;
+    let count = check(unsafe {
+        ffi::sa_simplesearch(
+            text.as_ptr(),
+            c_len(text.len())?,
+            sa.as_ptr(),
+            c_len(sa.len())?,
+            c as i32,
+            &mut left,
+        )
+    })?;
+    let left = left.max(0) as usize;
+    Ok(left..left + count as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b"how much wood would a woodchuck chuck";
+
+    #[cfg(feature = "crosscheck")]
+    #[test]
+    fn sort_with_trace() {
+        let traces: Vec<Vec<u8>> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    let mut trace = Vec::new();
+                    let sa = super::sort_with_trace(TEXT, &mut trace).unwrap();
+                    sa.verify().unwrap();
+                    // not traced
+                    sort(TEXT);
+                    trace
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .collect();
+        assert!(!traces[0].is_empty());
+        assert!(traces.iter().all(|t| *t == traces[0]));
+
+        let mut trace64 = Vec::new();
+        super::idx64::sort_with_trace(TEXT, &mut trace64).unwrap();
+        assert_eq!(trace64, traces[0]);
+    }
+
+    #[test]
+    fn version() {
+        assert!(super::version().starts_with("2."));
+    }
+
+    #[test]
+    fn idx64() {
+        let (_, sa32) = sort(TEXT).into_parts();
+        let sa64 = super::idx64::sort(TEXT);
+        sa64.verify().unwrap();
+        let (_, sa64) = sa64.into_parts();
+        assert!(sa32.iter().zip(sa64.iter()).all(|(&a, &b)| a as i64 == b));
+    }
+
+    #[test]
+    fn bwt_round_trip() {
+        let (_, sa) = sort(TEXT).into_parts();
+        let (bwt, pidx) = super::bwt(TEXT).unwrap();
+        assert_eq!(bw_transform(TEXT, &sa).unwrap(), (bwt.clone(), pidx));
+        assert_eq!(inverse_bwt(&bwt, pidx).unwrap(), TEXT);
+
+        let mut aux = vec![0; TEXT.len()];
+        assert_eq!(inverse_bwt_with_aux(&bwt, pidx, &mut aux).unwrap(), TEXT);
+
+        assert_eq!(inverse_bwt(&bwt, 0), Err(Error::InvalidArguments));
+        assert_eq!(inverse_bwt(b"a", 1).unwrap(), b"a");
+
+        // these used to read out of bounds
+        assert_eq!(inverse_bwt(b"ab", 1), Err(Error::InvalidBwt));
+        assert_eq!(
+            inverse_bwt_with_aux(b"ab", 1, &mut [0; 2]),
+            Err(Error::InvalidBwt)
+        );
+        let mut bad = sa.clone();
+        bad[0] = TEXT.len() as i32;
+        assert_eq!(bw_transform(TEXT, &bad), Err(Error::OutOfRange));
+        assert!(bw_transform(TEXT, &vec![0; TEXT.len()]).is_err());
+
+        // every short input, valid or not
+        for len in 2..6_usize {
+            for bits in 0..1 << len {
+                let input: Vec<u8> = (0..len).map(|i| b"ab"[bits >> i & 1]).collect();
+                for pidx in 1..=len as i32 {
+                    if let Ok(text) = inverse_bwt(&input, pidx) {
+                        assert_eq!(super::bwt(&text).unwrap(), (input.clone(), pidx));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn check_sa() {
+        let (_, mut sa) = sort(TEXT).into_parts();
+        assert_eq!(super::check_sa(TEXT, &sa, false), Ok(()));
+        assert_eq!(
+            super::check_sa(TEXT, &sa[1..], false),
+            Err(Error::LengthMismatch)
+        );
+
+        sa.swap(3, 4);
+        assert!(super::check_sa(TEXT, &sa, false).is_err());
+        sa[0] = TEXT.len() as i32;
+        assert_eq!(super::check_sa(TEXT, &sa, false), Err(Error::OutOfRange));
+    }
+
+    #[test]
+    fn search() {
+        let (_, sa) = sort(TEXT).into_parts();
+        let suffix_array = sacabase::SuffixArray::new(TEXT, sa.clone());
+        for pattern in &[&b"wood"[..], b"chuck", b"w", b"", b"z", b"how much"] {
+            assert_eq!(
+                super::search(TEXT, &sa, pattern).unwrap().len(),
+                suffix_array.count(pattern),
+                "count of {:?}",
+                pattern
+            );
+        }
+        for c in 0..=255u8 {
+            assert_eq!(
+                simple_search(TEXT, &sa, c).unwrap(),
+                suffix_array.range(&[c])
+            );
+        }
+
+        // the C versions would read out of bounds
+        let mut bad = sa.clone();
+        bad[0] = i32::MAX;
+        assert_eq!(super::search(TEXT, &bad, b"w"), Err(Error::OutOfRange));
+        assert_eq!(simple_search(TEXT, &bad, b'w'), Err(Error::OutOfRange));
+    }
+}
@@ -1,3 +1,10 @@
+// Follows the reference implementation from Kärkkäinen & Sanders closely.
+#![allow(
+    clippy::identity_op,
+    clippy::manual_div_ceil,
+    clippy::needless_range_loop
+)]
+
 /// Lexicographic order for pairs
 #[inline(always)]
 fn leq2(a1: usize, a2: usize, b1: usize, b2: usize) -> bool {
@@ -6,7 +13,7 @@ fn leq2(a1: usize, a2: usize, b1: usize, b2: usize) -> bool {
 
 /// Lexicographic order for triples
 #[inline(always)]
-fn leq3(a1: usize, a2: usize, b1: usize, b2: usize, a3: usize, b3: usize) -> bool {
+fn leq3(a1: usize, a2: usize, a3: usize, b1: usize, b2: usize, b3: usize) -> bool {
     (a1 < b1) || (a1 == b1 && leq2(a2, a3, b2, b3))
 }
 
@@ -73,7 +80,7 @@ pub fn suffix_array(T: &[usize], SA: &mut [usize], n: usize, K: usize) {
     // lsb radix sort the mod 1 and mod 2 triples
     radix_pass(&R[..], &mut SA12[..], &T[2..], n02, K);
     radix_pass(&SA12[..], &mut R[..], &T[1..], n02, K);
-    radix_pass(&R[..], &mut SA12[..], &T[..], n02, K);
+    radix_pass(&R[..], &mut SA12[..], T, n02, K);
 
     // Find lexicographic names of triples and
     // write them to the correct places in R
@@ -192,11 +199,62 @@ pub fn suffix_array(T: &[usize], SA: &mut [usize], n: usize, K: usize) {
     }
 }
 
+/// Sort suffixes of `text` and store their lexographic order
+/// in the given suffix array `sa`.
+/// Will panic if `sa.len()` != `text.len()`
+pub fn sort_in_place(text: &[u8], sa: &mut [usize]) {
+    assert_eq!(
+        text.len(),
+        sa.len(),
+        "text and suffix array should have same len"
+    );
+
+    let n = text.len();
+    if n == 1 {
+        sa[0] = 0;
+    } else if n >= 2 {
+        // shift bytes to 1..=256, so 0 can serve as the sentinel
+        let mut t = vec![0; n + 3];
+        for (x, &b) in t.iter_mut().zip(text) {
+            *x = b as usize + 1;
+        }
+        suffix_array(&t[..], sa, n, 256);
+    }
+}
+
+/// Sort suffixes
+pub fn sort(text: &[u8]) -> sacabase::SuffixArray<'_, usize> {
+    let mut sa = vec![0; text.len()];
+    sort_in_place(text, &mut sa);
+    sacabase::SuffixArray::new(text, sa)
+}
+
+/// DC3, as a `sacabase::Saca`
+pub struct Dc3;
+
+impl sacabase::Saca for Dc3 {
+    type Index = usize;
+
+    fn name(&self) -> &'static str {
+        "dc3"
+    }
+
+    fn max_len(&self) -> usize {
+        // the text is copied with three sentinels
+        usize::MAX - 3
+    }
+
+    fn sort_in_place(&self, text: &[u8], sa: &mut [usize]) {
+        sort_in_place(text, sa)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
+    #[allow(non_snake_case)]
     fn it_works() {
         let s = "Once upon a time, in a land most dreary";
         let mut T = vec![0usize; s.len() + 3];
@@ -210,11 +268,32 @@ mod tests {
 
         suffix_array(&T[..], &mut SA[..], n, K);
         for i in 0..(n - 1) {
-            println!("===============");
-            println!("suf(SA[{}]) = {:?}", i, &s[SA[i]..]);
-            println!("suf(SA[{}]) = {:?}", i + 1, &s[SA[i + 1]..]);
-            // FIXME: this is busted
-            // assert!(s[SA[i]..] < s[SA[i + 1]..])
+            assert!(s[SA[i]..] < s[SA[i + 1]..])
+        }
+    }
+
+    #[test]
+    fn sort() {
+        let mut random = sacabase::naive::Xorshift(0x9e37_79b9);
+
+        let mut inputs: Vec<Vec<u8>> = ["", "a", "ab", "ba", "aa", "aaa", "banana", "mmmmmmmmmm"]
+            .iter()
+            .map(|s| s.as_bytes().to_vec())
+            .collect();
+        inputs.push(b"\0\0\xff\0\xff\xff\0".to_vec());
+        for len in 2..64 {
+            inputs.push(random.text(len, b"ab"));
+            inputs.push(random.text(len, b"abcd"));
+        }
+        inputs.push(random.text(10_000, b"ab"));
+        inputs.push((0..=255).cycle().take(5000).collect());
+
+        for input in &inputs {
+            let sa = super::sort(input);
+            sa.verify().unwrap();
+            let (_, sa) = sa.into_parts();
+            let (_, expected) = sacabase::naive::sort(input).into_parts();
+            assert_eq!(sa, expected, "suffix array of {:?}", input);
         }
     }
 }
@@ -0,0 +1,37 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use sacabase::naive;
+
+// The first byte is the needle's length, the needle comes next, then the
+// text: short needles make for more matches.
+fuzz_target!(|data: &[u8]| {
+    let (&needle_len, rest) = match data.split_first() {
+        Some(x) => x,
+        None => return,
+    };
+    let (needle, text) = rest.split_at((needle_len as usize).min(rest.len()));
+
+    let sa = divsufsort::sort(text);
+
+    let expected = naive::find_all(text, needle);
+    let mut found: Vec<usize> = sa.find_all(needle).collect();
+    found.sort_unstable();
+    assert_eq!(found, expected, "find_all should return every occurrence");
+    assert_eq!(sa.count(needle), expected.len());
+    assert_eq!(sa.range(needle).len(), expected.len());
+
+    let table = sa.mlr_table();
+    assert_eq!(sa.range_mlr(&table, needle), sa.range(needle));
+
+    if text.is_empty() {
+        return;
+    }
+    let longest = naive::longest_match(text, needle).len();
+    for m in &[
+        sa.longest_substring_match(needle),
+        sa.longest_substring_match_mlr(&table, needle),
+    ] {
+        assert_eq!(m.len(), longest, "longest_substring_match should be longest");
+        assert_eq!(m.as_bytes(), &needle[..longest]);
+    }
+});
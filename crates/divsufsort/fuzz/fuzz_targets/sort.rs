@@ -0,0 +1,29 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+/// Inputs up to this size are also checked against a comparison sort,
+/// which is quadratic in the worst case
+const NAIVE_MAX_LEN: usize = 4096;
+
+fuzz_target!(|data: &[u8]| {
+    let sa = divsufsort::sort(data);
+    sa.verify().expect("divsufsort should sort all suffixes");
+    let (_, sa) = sa.into_parts();
+
+    let (_, c_sa) = cdivsufsort::sort(data).into_parts();
+    assert_eq!(sa, c_sa, "divsufsort and cdivsufsort should agree");
+
+    let (_, sa64) = divsufsort::idx64::sort(data).into_parts();
+    assert!(
+        sa.iter().zip(sa64.iter()).all(|(&a, &b)| a as i64 == b),
+        "divsufsort and its 64-bit build should agree"
+    );
+
+    if data.len() <= NAIVE_MAX_LEN {
+        let (_, naive) = sacabase::naive::sort(data).into_parts();
+        assert!(
+            sa.iter().zip(naive.iter()).all(|(&a, &b)| a as usize == b),
+            "divsufsort and a comparison sort should agree"
+        );
+    }
+});
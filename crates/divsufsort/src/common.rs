@@ -1,15 +1,21 @@
 use std::cmp::{Ordering, PartialEq, PartialOrd};
 use std::ops::{self, Add, AddAssign, Div, Index, IndexMut, Sub, SubAssign};
 
+pub use super::Idx;
+
 pub type Char = u8;
-pub type Idx = i32;
 
 pub const TR_INSERTIONSORT_THRESHOLD: Idx = 8;
 
 pub const SS_INSERTIONSORT_THRESHOLD: Idx = 8;
 pub const SS_BLOCKSIZE: Idx = 1024;
 
-pub const ALPHABET_SIZE: usize = u8::max_value() as usize + 1;
+// The 64-bit build needs deeper stacks, as in libdivsufsort
+const IDX64: bool = std::mem::size_of::<Idx>() == 8;
+pub const TR_STACKSIZE: usize = if IDX64 { 96 } else { 64 };
+pub const SS_SMERGE_STACKSIZE: usize = if IDX64 { 64 } else { 32 };
+
+pub const ALPHABET_SIZE: usize = u8::MAX as usize + 1;
 pub const BUCKET_A_SIZE: usize = ALPHABET_SIZE;
 pub const BUCKET_B_SIZE: usize = ALPHABET_SIZE * ALPHABET_SIZE;
 
@@ -99,6 +105,28 @@ impl<'a> SuffixArray<'a> {
     }
 }
 
+/// What `sssort` needs from the array it works in: a `SuffixArray`, or
+/// the parts of one a thread is given when sorting in parallel.
+/// Indices are those of the whole suffix array either way.
+pub trait SortArray:
+    Index<Idx, Output = Idx> + IndexMut<Idx> + Index<SAPtr, Output = Idx> + IndexMut<SAPtr>
+{
+    fn swap<A: Into<Idx>, B: Into<Idx>>(&mut self, a: A, b: B);
+
+    fn range<'b, I: Into<Idx>>(&'b mut self, range: ops::Range<I>) -> SuffixArray<'b>;
+}
+
+impl<'a> SortArray for SuffixArray<'a> {
+    #[inline(always)]
+    fn swap<A: Into<Idx>, B: Into<Idx>>(&mut self, a: A, b: B) {
+        SuffixArray::swap(self, a, b)
+    }
+
+    fn range<'b, I: Into<Idx>>(&'b mut self, range: ops::Range<I>) -> SuffixArray<'b> {
+        SuffixArray::range(self, range)
+    }
+}
+
 // ---------- Immutable variant ----------- *shakes fist at borrowck*
 
 // Indexes of all suffixes in lexicographical order
@@ -199,10 +227,10 @@ impl Sub<Self> for SAPtr {
     }
 }
 
-impl Into<Idx> for SAPtr {
+impl From<SAPtr> for Idx {
     #[inline(always)]
-    fn into(self) -> Idx {
-        self.0
+    fn from(ptr: SAPtr) -> Self {
+        ptr.0
     }
 }
 
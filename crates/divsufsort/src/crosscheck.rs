@@ -1,55 +1,151 @@
-#[cfg(feature = "crosscheck")]
-use once_cell::sync::Lazy;
+//! Crosscheck traces, to compare this port with the C version step by step.
+//!
+//! Both write the same format: one record per line, with tab-separated
+//! fields.
+//!
+//! ```text
+//! <phase>\tmsg\t<message>
+//! <phase>\tsa\t<label>\t<v0> <v1> ...
+//! <phase>\ta\t<label>\t<v0> <v1> ...
+//! <phase>\tbstar\t<label>\t<v0> <v1> ...
+//! ```
+//!
+//! `phase` is the source file the record comes from (`divsufsort`,
+//! `sssort` or `trsort`). `msg` records are the `crosscheck!` messages,
+//! the others are dumps of a range of the suffix array, of bucket A,
+//! and of the B* buckets (row-major).
+//!
+//! With the `crosscheck` feature, `sort_with_trace` (here and in
+//! cdivsufsort) writes the trace of a sort to any `Write`. Records are
+//! only written by the thread that called it, so sorts on other threads
+//! don't end up in each other's traces. `first_divergence` compares two
+//! traces.
 
 use crate::common::{ABucket, BMixBucket, Idx, SuffixArray, ALPHABET_SIZE};
 use std::{
-    fs::File,
-    io::{BufWriter, Write},
-    sync::Mutex,
+    cell::RefCell,
+    collections::VecDeque,
+    fmt,
+    io::{self, BufRead, Write},
+    mem,
+    ptr::NonNull,
 };
 
+/// The trace of the sort running on this thread, if any
+#[cfg(feature = "crosscheck")]
+struct Sink {
+    out: NonNull<dyn Write>,
+    /// First error writing to `out`, after which records are dropped
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "crosscheck")]
+thread_local! {
+    static SINK: RefCell<Option<Sink>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` with records written on this thread going to `trace`, and
+/// returns the first error writing them, if any
+#[cfg(feature = "crosscheck")]
+pub fn with_trace<R>(trace: &mut dyn Write, f: impl FnOnce() -> R) -> io::Result<R> {
+    /// Restores the previous sink, even if `f` panics
+    struct Guard(Option<Sink>);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            SINK.with(|sink| *sink.borrow_mut() = self.0.take());
+        }
+    }
+
+    // `trace` outlives the sink: the guard removes it before returning
+    let out: NonNull<dyn Write + '_> = NonNull::from(trace);
+    let out: NonNull<dyn Write> = unsafe { mem::transmute(out) };
+    let previous = SINK.with(|sink| sink.borrow_mut().replace(Sink { out, error: None }));
+    let guard = Guard(previous);
+
+    let res = f();
+    let error = SINK.with(|sink| sink.borrow_mut().as_mut().unwrap().error.take());
+    drop(guard);
+    match error {
+        Some(e) => Err(e),
+        None => Ok(res),
+    }
+}
+
+/// Writes a record with `f`, if there's a trace on this thread
 #[cfg(feature = "crosscheck")]
-pub static CROSSCHECK_FILE: Lazy<Mutex<BufWriter<File>>> = Lazy::new(|| {
-    std::fs::create_dir_all("crosscheck").unwrap();
-    Mutex::new(BufWriter::new(File::create("crosscheck/rust").unwrap()))
-});
+fn write_record(f: impl FnOnce(&mut dyn Write) -> io::Result<()>) {
+    SINK.with(|sink| {
+        if let Some(sink) = sink.borrow_mut().as_mut() {
+            if sink.error.is_none() {
+                if let Err(e) = f(unsafe { sink.out.as_mut() }) {
+                    sink.error = Some(e);
+                }
+            }
+        }
+    });
+}
+
+/// Used to flush the global trace file, which is gone: `sort_with_trace`
+/// writes records to its `Write` as they come. Does nothing.
+#[deprecated(since = "1.1.0", note = "use `sort_with_trace` instead")]
+pub fn flush() {}
+
+/// Phase of a record written from module `module_path`
+pub fn phase(module_path: &str) -> &str {
+    module_path.rsplit("::").next().unwrap()
+}
+
+/// Writes a `msg` record
+#[cfg(feature = "crosscheck")]
+pub fn write_msg(phase: &str, msg: fmt::Arguments) {
+    write_record(|f| writeln!(f, "{}\tmsg\t{}", phase, msg));
+}
+
+/// Writes a dump record
+#[cfg(feature = "crosscheck")]
+pub fn write_dump<T: fmt::Display>(
+    phase: &str,
+    kind: &str,
+    label: &str,
+    values: impl IntoIterator<Item = T>,
+) {
+    write_record(|f| {
+        write!(f, "{}\t{}\t{}\t", phase, kind, label)?;
+        for (i, x) in values.into_iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", x)?;
+        }
+        writeln!(f)
+    });
+}
 
 #[macro_export]
 macro_rules! crosscheck {
     ($($arg: expr),*) => {
         #[cfg(feature = "crosscheck")]
         {
-            use std::io::Write;
-            let mut f = crate::crosscheck::CROSSCHECK_FILE.lock().unwrap();
-            writeln!(f, $($arg),*).unwrap();
+            $crate::crosscheck::write_msg(
+                $crate::crosscheck::phase(module_path!()),
+                format_args!($($arg),*),
+            );
         }
     };
 }
 
-pub fn flush() {
-    #[cfg(feature = "crosscheck")]
-    {
-        let mut f = crate::crosscheck::CROSSCHECK_FILE.lock().unwrap();
-        f.flush().unwrap();
-    }
-}
-
 #[macro_export]
 macro_rules! SA_dump {
     ($SA: expr, $label: expr) => {
         #[cfg(feature = "crosscheck")]
         {
-            use std::io::Write;
-            let mut f = crate::crosscheck::CROSSCHECK_FILE.lock().unwrap();
-
-            writeln!(f, ":: {}", $label).unwrap();
-            for i in 0..$SA.0.len() {
-                write!(f, "{} ", $SA.0[i]).unwrap();
-                if (i + 1) % 25 == 0 {
-                    writeln!(f).unwrap();
-                }
-            }
-            writeln!(f).unwrap();
+            $crate::crosscheck::write_dump(
+                $crate::crosscheck::phase(module_path!()),
+                "sa",
+                $label,
+                $SA.0.iter(),
+            );
         }
     };
 }
@@ -59,8 +155,12 @@ macro_rules! A_dump {
     ($A: expr, $label: expr) => {
         #[cfg(feature = "crosscheck")]
         {
-            crosscheck!(":: {}", $label);
-            crosscheck!("A = {:?}", $A.0);
+            $crate::crosscheck::write_dump(
+                $crate::crosscheck::phase(module_path!()),
+                "a",
+                $label,
+                $A.0.iter(),
+            );
         }
     };
 }
@@ -70,15 +170,125 @@ macro_rules! BSTAR_dump {
     ($B: expr, $label: expr) => {
         #[cfg(feature = "crosscheck")]
         {
-            use std::io::Write;
-            let mut f = crate::crosscheck::CROSSCHECK_FILE.lock().unwrap();
+            let B = &$B;
+            $crate::crosscheck::write_dump(
+                $crate::crosscheck::phase(module_path!()),
+                "bstar",
+                $label,
+                (0..(ALPHABET_SIZE as Idx))
+                    .flat_map(|ii| (0..(ALPHABET_SIZE as Idx)).map(move |jj| B.bstar()[(ii, jj)])),
+            );
+        }
+    };
+}
+
+/// Where two traces stop agreeing, see `first_divergence`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Index of the first differing record
+    pub record: usize,
+    /// The records just before, which both traces agree on
+    pub context: Vec<String>,
+    /// The first differing record of each trace, `None` if it ended
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
 
-            writeln!(f, "{} B* dump:", $label).unwrap();
-            for ii in 0..(ALPHABET_SIZE as Idx) {
-                for jj in 0..(ALPHABET_SIZE as Idx) {
-                    writeln!(f, "{} B*[{},{}]={}", $label, ii, jj, $B.bstar()[(ii, jj)]).unwrap();
+impl Divergence {
+    /// Phase the divergence occurred in
+    pub fn phase(&self) -> &str {
+        let record = self.left.as_ref().or(self.right.as_ref()).unwrap();
+        record.split('\t').next().unwrap()
+    }
+
+    /// For two dumps with the same label, the first differing value:
+    /// its position, and both values
+    pub fn first_differing_value(&self) -> Option<(usize, &str, &str)> {
+        let (left, right) = (self.left.as_ref()?, self.right.as_ref()?);
+        let (l, r): (Vec<&str>, Vec<&str>) =
+            (left.split('\t').collect(), right.split('\t').collect());
+        if l.len() != 4 || r.len() != 4 || l[..3] != r[..3] {
+            return None;
+        }
+        let mut l = l[3].split(' ');
+        let mut r = r[3].split(' ');
+        let mut i = 0;
+        loop {
+            match (l.next(), r.next()) {
+                (None, None) => return None,
+                (x, y) if x != y => return Some((i, x.unwrap_or("(end)"), y.unwrap_or("(end)"))),
+                _ => i += 1,
+            }
+        }
+    }
+}
+
+/// Shortens records, which can be huge dumps, for display
+fn ellipsize(s: &str) -> String {
+    const MAX: usize = 160;
+    match s.char_indices().nth(MAX) {
+        Some((i, _)) => format!("{}... ({} bytes)", &s[..i], s.len()),
+        None => s.to_string(),
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "first divergence at record {}, in phase {}",
+            self.record,
+            self.phase()
+        )?;
+        for record in &self.context {
+            writeln!(f, "    {}", ellipsize(record))?;
+        }
+        let show = |r: &Option<String>| match r {
+            Some(r) => ellipsize(r),
+            None => "(end of trace)".to_string(),
+        };
+        writeln!(f, "  < {}", show(&self.left))?;
+        write!(f, "  > {}", show(&self.right))?;
+        if let Some((i, l, r)) = self.first_differing_value() {
+            write!(f, "\n  value {} differs: {} vs {}", i, l, r)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two traces record by record, and returns the first
+/// divergence with up to `context` records before it, or `None` if
+/// they're identical.
+pub fn first_divergence<L: BufRead, R: BufRead>(
+    left: L,
+    right: R,
+    context: usize,
+) -> io::Result<Option<Divergence>> {
+    let mut left = left.lines();
+    let mut right = right.lines();
+    let mut before = VecDeque::with_capacity(context + 1);
+    let mut record = 0;
+    loop {
+        let l = left.next().transpose()?;
+        let r = right.next().transpose()?;
+        if l == r {
+            match l {
+                None => return Ok(None),
+                Some(l) => {
+                    before.push_back(l);
+                    if before.len() > context {
+                        before.pop_front();
+                    }
                 }
             }
+        } else {
+            return Ok(Some(Divergence {
+                record,
+                context: before.into(),
+                left: l,
+                right: r,
+            }));
         }
-    };
+        record += 1;
+    }
 }
@@ -1,15 +1,30 @@
-use crate::{common::*, crosscheck, crosscheck::*, sssort, trsort, SA_dump};
+use super::{common::*, sssort, trsort};
+use crate::{crosscheck, crosscheck::*, SA_dump};
+
+#[cfg(feature = "parallel")]
+use super::parallel::{self, default_threads};
+
+#[cfg(not(feature = "parallel"))]
+fn default_threads(n: usize) -> usize {
+    1
+}
 
 pub fn divsufsort(T: &[Char], SA: &mut [Idx]) {
+    divsufsort_threads(T, SA, default_threads(T.len()));
+}
+
+/// Same as `divsufsort`, but sorts type B* buckets on up to `threads`
+/// threads when the `parallel` feature is enabled.
+pub fn divsufsort_threads(T: &[Char], SA: &mut [Idx], threads: usize) {
     assert_eq!(
         T.len(),
         SA.len(),
         "text and suffix array should have same len"
     );
     assert!(
-        T.len() < i32::max_value() as usize,
+        T.len() < Idx::MAX as usize,
         "text too large, should not exceed {} bytes",
-        i32::max_value() - 1
+        Idx::MAX - 1
     );
 
     let n = T.len();
@@ -32,17 +47,58 @@ pub fn divsufsort(T: &[Char], SA: &mut [Idx]) {
     let mut SA = SuffixArray(SA);
 
     // Suffixsort.
-    let res = sort_typeBstar(&T, &mut SA);
+    let res = sort_typeBstar(&T, &mut SA, threads);
     construct_SA(&T, &mut SA, res.A, res.B, res.m);
 }
 
+/// Constructs the Burrows-Wheeler transform of `T` into `U`, using `A`
+/// as scratch space. Returns the primary index.
+pub fn divbwt(T: &[Char], U: &mut [Char], A: &mut [Idx]) -> Idx {
+    assert_eq!(T.len(), U.len(), "text and output should have same len");
+    assert_eq!(
+        T.len(),
+        A.len(),
+        "text and temporary array should have same len"
+    );
+    assert!(
+        T.len() < Idx::MAX as usize,
+        "text too large, should not exceed {} bytes",
+        Idx::MAX - 1
+    );
+
+    let n = T.len();
+    if n <= 1 {
+        if n == 1 {
+            U[0] = T[0];
+        }
+        return n as Idx;
+    }
+
+    let T = Text(T);
+    let mut B = SuffixArray(A);
+
+    // Burrows-Wheeler Transform.
+    let res = sort_typeBstar(&T, &mut B, default_threads(n));
+    let pidx = construct_BWT(&T, &mut B, res.A, res.B, res.m);
+
+    // Copy to output string.
+    U[0] = T[T.len() - 1];
+    for i in 0..pidx {
+        U[i as usize + 1] = B[i] as Char;
+    }
+    for i in (pidx + 1)..T.len() {
+        U[i as usize] = B[i] as Char;
+    }
+    pidx + 1
+}
+
 struct SortTypeBstarResult {
     A: ABucket,
     B: BMixBucket,
     m: Idx,
 }
 
-fn sort_typeBstar(T: &Text, SA: &mut SuffixArray) -> SortTypeBstarResult {
+fn sort_typeBstar(T: &Text, SA: &mut SuffixArray, threads: usize) -> SortTypeBstarResult {
     let n = T.len();
 
     // Initialize bucket arrays
@@ -160,43 +216,50 @@ fn sort_typeBstar(T: &Text, SA: &mut SuffixArray) -> SortTypeBstarResult {
         SA[B.bstar()[(c0, c1)]] = m - 1;
 
         // Sort the type B* substrings using sssort.
-        let buf = SAPtr(m);
-        let bufsize = n - (2 * m);
-
-        // init (outer)
-        c0 = ALPHABET_SIZE as Idx - 2;
-        j = m;
-        while 0 < j {
-            // init (inner)
-            c1 = ALPHABET_SIZE as Idx - 1;
-            while c0 < c1 {
-                // body (inner)
-                i = B.bstar()[(c0, c1)];
-
-                if (1 < (j - i)) {
-                    SA_dump!(&SA.range(i..j), "sssort(A)");
-                    sssort::sssort(
-                        T,
-                        SA,
-                        PAb,
-                        SAPtr(i),
-                        SAPtr(j),
-                        buf,
-                        bufsize,
-                        2,
-                        n,
-                        SA[i] == (m - 1),
-                    );
-                    SA_dump!(&SA.range(i..j), "sssort(B)");
+        #[cfg(feature = "parallel")]
+        let sorted = parallel::sssort_buckets(T, SA, &mut B, m, threads);
+        #[cfg(not(feature = "parallel"))]
+        let sorted = false;
+
+        if !sorted {
+            let buf = SAPtr(m);
+            let bufsize = n - (2 * m);
+
+            // init (outer)
+            c0 = ALPHABET_SIZE as Idx - 2;
+            j = m;
+            while 0 < j {
+                // init (inner)
+                c1 = ALPHABET_SIZE as Idx - 1;
+                while c0 < c1 {
+                    // body (inner)
+                    i = B.bstar()[(c0, c1)];
+
+                    if (1 < (j - i)) {
+                        SA_dump!(&SA.range(i..j), "sssort(A)");
+                        sssort::sssort(
+                            T,
+                            SA,
+                            PAb,
+                            SAPtr(i),
+                            SAPtr(j),
+                            buf,
+                            bufsize,
+                            2,
+                            n,
+                            SA[i] == (m - 1),
+                        );
+                        SA_dump!(&SA.range(i..j), "sssort(B)");
+                    }
+
+                    // iter (inner)
+                    j = i;
+                    c1 -= 1;
                 }
 
-                // iter (inner)
-                j = i;
-                c1 -= 1;
+                // iter (outer)
+                c0 -= 1;
             }
-
-            // iter (outer)
-            c0 -= 1;
         }
 
         // Compute ranks of type B* substrings
@@ -449,3 +512,114 @@ fn construct_SA(T: &Text, SA: &mut SuffixArray, mut A: ABucket, mut B: BMixBucke
         }
     }
 }
+
+/// Constructs the burrows-wheeler transformed string directly
+/// by using the sorted order of type B* suffixes.
+fn construct_BWT(T: &Text, SA: &mut SuffixArray, mut A: ABucket, mut B: BMixBucket, m: Idx) -> Idx {
+    let n = T.len() as Idx;
+    let mut i: SAPtr;
+    let mut j: SAPtr;
+    let mut k: Idx;
+    let mut orig: SAPtr;
+    let mut s: Idx;
+    let mut c0: Idx;
+    let mut c1: Idx;
+    let mut c2: Idx;
+
+    if 0 < m {
+        // Construct the sorted order of type B suffixes by using the
+        // sorted order of type B* suffixes
+        c1 = ALPHABET_SIZE as Idx - 2;
+        while 0 <= c1 {
+            // Scan the suffix array from right to left
+            i = SAPtr(B.bstar()[(c1, c1 + 1)]);
+            j = SAPtr(A[c1 + 1] - 1);
+            k = 0;
+            c2 = -1;
+
+            while i <= j {
+                s = SA[j];
+                if (0 < s) {
+                    assert_eq!(T.get(s), c1);
+                    assert!((s + 1) < n);
+                    assert!(T[s] <= T[s + 1]);
+                    assert!(T[s - 1] <= T[s]);
+
+                    s -= 1;
+                    c0 = T.get(s);
+                    SA[j] = !c0;
+                    if (0 < s) && (T.get(s - 1) > c0) {
+                        s = !s;
+                    }
+                    if (c0 != c2) {
+                        if (0 <= c2) {
+                            B.b()[(c2, c1)] = k;
+                        }
+                        c2 = c0;
+                        k = B.b()[(c2, c1)];
+                    }
+                    assert!(k < j);
+                    SA[k] = s;
+                    k -= 1;
+                } else if (s != 0) {
+                    SA[j] = !s;
+                } else {
+                    debug_assert_eq!(T.get(s), c1);
+                }
+
+                // iter
+                j -= 1;
+            }
+
+            // iter
+            c1 -= 1;
+        }
+    }
+
+    // Construct the BWTed string by using the sorted order of type B suffixes
+    c2 = T.get(n - 1);
+    k = A[c2];
+    SA[k] = if T.get(n - 2) < c2 {
+        !T.get(n - 2)
+    } else {
+        n - 1
+    };
+    k += 1;
+    // Scan the suffix array from left to right
+    {
+        // init
+        i = SAPtr(0);
+        j = SAPtr(n);
+        orig = SAPtr(0);
+
+        while i < j {
+            s = SA[i];
+            if 0 < s {
+                assert!(T[s - 1] >= T[s]);
+                s -= 1;
+                c0 = T.get(s);
+                SA[i] = c0;
+                if (0 < s) && (T.get(s - 1) < c0) {
+                    s = !T.get(s - 1);
+                }
+                if (c0 != c2) {
+                    A[c2] = k;
+                    c2 = c0;
+                    k = A[c2];
+                }
+                assert!(i < k);
+                SA[k] = s;
+                k += 1;
+            } else if s != 0 {
+                SA[i] = !s;
+            } else {
+                orig = i;
+            }
+
+            // iter
+            i += 1;
+        }
+    }
+
+    orig.0
+}
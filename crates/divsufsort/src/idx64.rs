@@ -0,0 +1,99 @@
+//! 64-bit variant of the port, for inputs of 2 GiB or more.
+//!
+//! Like libdivsufsort's `divsufsort64`, this is the very same code,
+//! built a second time with a 64-bit `Idx`.
+
+#[path = "common.rs"]
+mod common;
+#[path = "divsufsort.rs"]
+mod divsufsort;
+#[cfg(feature = "parallel")]
+#[path = "parallel.rs"]
+mod parallel;
+#[path = "sssort.rs"]
+mod sssort;
+#[path = "trsort.rs"]
+mod trsort;
+#[path = "utils.rs"]
+mod utils;
+
+/// Index type of the suffix arrays built by this module
+pub type Idx = i64;
+
+/// Sort suffixes of `text` and store their lexographic order
+/// in the given suffix array `sa`.
+/// Will panic if `sa.len()` != `text.len()`
+pub fn sort_in_place(text: &[u8], sa: &mut [Idx]) {
+    divsufsort::divsufsort(text, sa);
+}
+
+/// Sort suffixes
+pub fn sort(text: &[u8]) -> sacabase::SuffixArray<'_, Idx> {
+    let mut sa = vec![0; text.len()];
+    sort_in_place(text, &mut sa);
+    sacabase::SuffixArray::new(text, sa)
+}
+
+/// Same as `sort`, but writes a crosscheck trace of the sort to `trace`,
+/// see `divsufsort::sort_with_trace`
+#[cfg(feature = "crosscheck")]
+pub fn sort_with_trace<'a, W: std::io::Write>(
+    text: &'a [u8],
+    trace: &mut W,
+) -> std::io::Result<sacabase::SuffixArray<'a, Idx>> {
+    let mut sa = vec![0; text.len()];
+    crate::crosscheck::with_trace(trace, || divsufsort::divsufsort_threads(text, &mut sa, 1))?;
+    Ok(sacabase::SuffixArray::new(text, sa))
+}
+
+/// The 64-bit Rust port of divsufsort, as a `sacabase::Saca`
+pub struct DivSufSort;
+
+impl sacabase::Saca for DivSufSort {
+    type Index = Idx;
+
+    fn name(&self) -> &'static str {
+        "divsufsort64"
+    }
+
+    fn max_len(&self) -> usize {
+        Idx::MAX as usize - 1
+    }
+
+    fn sort_in_place(&self, text: &[u8], sa: &mut [Idx]) {
+        sort_in_place(text, sa)
+    }
+}
+
+/// Burrows-Wheeler transform of `text`, see `divsufsort::bwt`
+pub fn bwt(text: &[u8]) -> (Vec<u8>, Idx) {
+    let mut bwt = vec![0; text.len()];
+    let mut tmp = vec![0; text.len()];
+    let pidx = divsufsort::divbwt(text, &mut bwt, &mut tmp);
+    (bwt, pidx)
+}
+
+/// Burrows-Wheeler transform of `text`, see `divsufsort::bw_transform`
+pub fn bw_transform(text: &[u8], sa: &[Idx]) -> (Vec<u8>, Idx) {
+    let mut bwt = vec![0; text.len()];
+    let pidx = utils::bw_transform(text, &mut bwt, sa);
+    (bwt, pidx)
+}
+
+/// Inverse Burrows-Wheeler transform, see `divsufsort::inverse_bwt`
+pub fn inverse_bwt(bwt: &[u8], pidx: Idx) -> Result<Vec<u8>, crate::InvalidBwt> {
+    let mut text = vec![0; bwt.len()];
+    utils::inverse_bw_transform(bwt, &mut text, None, pidx)?;
+    Ok(text)
+}
+
+/// Inverse Burrows-Wheeler transform, see `divsufsort::inverse_bwt_with_aux`
+pub fn inverse_bwt_with_aux(
+    bwt: &[u8],
+    pidx: Idx,
+    aux: &mut [Idx],
+) -> Result<Vec<u8>, crate::InvalidBwt> {
+    let mut text = vec![0; bwt.len()];
+    utils::inverse_bw_transform(bwt, &mut text, Some(aux), pidx)?;
+    Ok(text)
+}
@@ -4,16 +4,37 @@
 #![allow(unused_mut)]
 #![allow(unused_imports)]
 #![allow(dead_code)]
+// The port mirrors the C sources closely, on purpose.
+#![allow(
+    clippy::double_must_use,
+    clippy::identity_op,
+    clippy::let_and_return,
+    clippy::needless_late_init,
+    clippy::needless_return,
+    clippy::neg_cmp_op_on_partial_ord,
+    clippy::nonminimal_bool,
+    clippy::too_many_arguments,
+    redundant_semicolons
+)]
+// `idx64` builds the same modules again, with a wider `Idx`.
+#![allow(clippy::duplicate_mod)]
 
 mod common;
 pub mod crosscheck;
 mod divsufsort;
+pub mod idx64;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod sssort;
 mod trsort;
+mod utils;
 
-use common::Idx;
 use sacabase::SuffixArray;
 
+/// Index type of the suffix arrays built by this crate.
+/// See `idx64` for inputs of 2 GiB or more.
+pub type Idx = i32;
+
 /// Sort suffixes of `text` and store their lexographic order
 /// in the given suffix array `sa`.
 /// Will panic if `sa.len()` != `text.len()`
@@ -21,13 +42,103 @@ pub fn sort_in_place(text: &[u8], sa: &mut [Idx]) {
     divsufsort::divsufsort(text, sa);
 }
 
-//// Sort suffixes
-pub fn sort(text: &[u8]) -> sacabase::SuffixArray<Idx> {
+/// Same as `sort_in_place`, but sorts type B* buckets on up to `threads`
+/// threads. `sort_in_place` uses one per core, for large enough inputs.
+#[cfg(feature = "parallel")]
+pub fn sort_in_place_with_threads(text: &[u8], sa: &mut [Idx], threads: usize) {
+    divsufsort::divsufsort_threads(text, sa, threads);
+}
+
+/// Sort suffixes
+pub fn sort(text: &[u8]) -> sacabase::SuffixArray<'_, Idx> {
     let mut sa = vec![0; text.len()];
     sort_in_place(text, &mut sa);
     sacabase::SuffixArray::new(text, sa)
 }
 
+/// Same as `sort`, but writes a crosscheck trace of the sort to `trace`,
+/// see the `crosscheck` module. Sorts on the calling thread only, so the
+/// trace can be compared with cdivsufsort's.
+#[cfg(feature = "crosscheck")]
+pub fn sort_with_trace<'a, W: std::io::Write>(
+    text: &'a [u8],
+    trace: &mut W,
+) -> std::io::Result<sacabase::SuffixArray<'a, Idx>> {
+    let mut sa = vec![0; text.len()];
+    crosscheck::with_trace(trace, || divsufsort::divsufsort_threads(text, &mut sa, 1))?;
+    Ok(sacabase::SuffixArray::new(text, sa))
+}
+
+/// The Rust port of divsufsort, as a `sacabase::Saca`
+pub struct DivSufSort;
+
+impl sacabase::Saca for DivSufSort {
+    type Index = Idx;
+
+    fn name(&self) -> &'static str {
+        "divsufsort"
+    }
+
+    fn max_len(&self) -> usize {
+        Idx::MAX as usize - 1
+    }
+
+    fn sort_in_place(&self, text: &[u8], sa: &mut [Idx]) {
+        sort_in_place(text, sa)
+    }
+}
+
+/// Burrows-Wheeler transform of `text`, built directly from the sorted
+/// type B* suffixes, without materializing the suffix array.
+/// Returns the transformed text and its primary index.
+pub fn bwt(text: &[u8]) -> (Vec<u8>, Idx) {
+    let mut bwt = vec![0; text.len()];
+    let mut tmp = vec![0; text.len()];
+    let pidx = divsufsort::divbwt(text, &mut bwt, &mut tmp);
+    (bwt, pidx)
+}
+
+/// Burrows-Wheeler transform of `text`, using its suffix array `sa`.
+/// Returns the transformed text and its primary index.
+pub fn bw_transform(text: &[u8], sa: &[Idx]) -> (Vec<u8>, Idx) {
+    let mut bwt = vec![0; text.len()];
+    let pidx = utils::bw_transform(text, &mut bwt, sa);
+    (bwt, pidx)
+}
+
+/// Error returned by `inverse_bwt` when its input isn't the Burrows-Wheeler
+/// transform of any text, with that primary index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidBwt;
+
+impl std::fmt::Display for InvalidBwt {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "invalid Burrows-Wheeler transform")
+    }
+}
+
+impl std::error::Error for InvalidBwt {}
+
+/// Inverse Burrows-Wheeler transform, given the transformed text and
+/// the primary index returned by `bwt` or `bw_transform`.
+pub fn inverse_bwt(bwt: &[u8], pidx: Idx) -> Result<Vec<u8>, InvalidBwt> {
+    let mut text = vec![0; bwt.len()];
+    utils::inverse_bw_transform(bwt, &mut text, None, pidx)?;
+    Ok(text)
+}
+
+/// Same as `inverse_bwt`, but uses `aux` (which should have the same
+/// length as `bwt`) as scratch space instead of allocating it.
+pub fn inverse_bwt_with_aux(
+    bwt: &[u8],
+    pidx: Idx,
+    aux: &mut [Idx],
+) -> Result<Vec<u8>, InvalidBwt> {
+    let mut text = vec![0; bwt.len()];
+    utils::inverse_bw_transform(bwt, &mut text, Some(aux), pidx)?;
+    Ok(text)
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -80,13 +191,251 @@ mod tests {
         sort(include_bytes!("./testdata/crash-16356e91966a827f79e49167170194fc3088a7ab"));
     }
 
-    #[test]
-    fn shruggy() {
-        sort(r#"¯\_(ツ)_/¯"#.as_bytes());
-    }
-
     fn sort(s: &[u8]) {
         let sa = super::sort(s);
         sa.verify().unwrap();
     }
+
+    #[test]
+    fn naive() {
+        use sacabase::naive;
+
+        let mut random = naive::Xorshift(0x2545_f491);
+        let mut inputs: Vec<Vec<u8>> = ["", "a", "ab", "ba", "aa", "banana", "mmmmmmmmmm"]
+            .iter()
+            .map(|s| s.as_bytes().to_vec())
+            .collect();
+        inputs.push(r#"¯\_(ツ)_/¯"#.as_bytes().to_vec());
+        inputs.push(b"\0\0\xff\0\xff\xff\0".to_vec());
+        for len in 2..64 {
+            inputs.push(random.text(len, b"ab"));
+            inputs.push(random.text(len, b"acgt"));
+        }
+        inputs.push(random.text(5000, b"ab"));
+        inputs.push(b"abracadabra".repeat(300));
+        inputs.push(include_bytes!("./testdata/fuzz1").to_vec());
+        inputs.push(include_bytes!("./testdata/fuzz3").to_vec());
+
+        for input in &inputs {
+            let (_, sa) = super::sort(input).into_parts();
+            let (_, expected) = naive::sort(input).into_parts();
+            assert!(
+                sa.iter().map(|&x| x as usize).eq(expected.iter().cloned()),
+                "suffix array of {:?}",
+                input
+            );
+            let sa = sacabase::SuffixArray::new(&input[..], sa);
+
+            for i in (0..input.len()).step_by(input.len() / 4 + 1) {
+                let needle = &input[i..input.len().min(i + 3)];
+                let mut found: Vec<usize> = sa.find_all(needle).collect();
+                found.sort_unstable();
+                assert_eq!(found, naive::find_all(input, needle));
+
+                let needle = [needle, b"!"].concat();
+                let m = sa.longest_substring_match(&needle);
+                assert_eq!(m.len(), naive::longest_match(input, &needle).len());
+            }
+        }
+    }
+
+    #[test]
+    fn idx64() {
+        for s in &[
+            &b"banana"[..],
+            &include_bytes!("./testdata/fuzz1")[..],
+            &include_bytes!("./testdata/fuzz2")[..],
+            &include_bytes!("./testdata/fuzz3")[..],
+            &include_bytes!("./testdata/crash-90b42d1c55ee90a8b004fb9db1853429ceb4c4ba")[..],
+        ] {
+            let (_, sa32) = super::sort(s).into_parts();
+            let sa64 = super::idx64::sort(s);
+            sa64.verify().unwrap();
+            let (_, sa64) = sa64.into_parts();
+            assert!(sa32.iter().zip(sa64.iter()).all(|(&a, &b)| a as i64 == b));
+
+            let (bwt, pidx) = super::idx64::bwt(s);
+            assert_eq!(super::bwt(s), (bwt.clone(), pidx as i32));
+            assert_eq!(super::idx64::inverse_bwt(&bwt, pidx).unwrap(), *s);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel() {
+        // long enough to be sorted on several threads by default
+        let random = sacabase::naive::Xorshift(0x2545_f491).text(1 << 17, b"acgt");
+        let repetitive = b"abracadabra".repeat(1 << 13);
+        // every other suffix is type B*, leaving no room for buffers
+        let zigzag: Vec<u8> = random
+            .chunks(2)
+            .flat_map(|c| vec![c[0], c[1] + 128])
+            .collect();
+
+        for s in &[
+            &b"banana"[..],
+            &include_bytes!("./testdata/fuzz1")[..],
+            &include_bytes!("./testdata/fuzz2")[..],
+            &include_bytes!("./testdata/fuzz3")[..],
+            &include_bytes!("./testdata/crash-90b42d1c55ee90a8b004fb9db1853429ceb4c4ba")[..],
+            &random[..],
+            &repetitive[..],
+            &zigzag[..],
+        ] {
+            let mut serial = vec![0; s.len()];
+            super::sort_in_place_with_threads(s, &mut serial, 1);
+            for &threads in &[2, 3, 8] {
+                let mut parallel = vec![0; s.len()];
+                super::sort_in_place_with_threads(s, &mut parallel, threads);
+                assert_eq!(serial, parallel, "{} threads", threads);
+            }
+            super::sort(s).verify().unwrap();
+        }
+    }
+
+    #[test]
+    fn saca() {
+        use sacabase::{DynSaca, Saca};
+
+        let s = include_bytes!("./testdata/fuzz1");
+        let (_, expected) = super::sort(s).into_parts();
+        let (_, sa) = super::DivSufSort.sort(s).into_parts();
+        assert_eq!(sa, expected);
+
+        let saca: &dyn DynSaca = &super::idx64::DivSufSort;
+        assert_eq!(saca.name(), "divsufsort64");
+        assert_eq!(saca.index_type(), "i64");
+        let (_, sa) = saca.sort_usize(s).into_parts();
+        assert!(sa.iter().zip(expected.iter()).all(|(&a, &b)| a == b as usize));
+    }
+
+    #[test]
+    fn bwt_naive() {
+        for s in &["", "a", "ab", "ba", "aa", "banana", "abracadabra", "mmmmmm"] {
+            let s = s.as_bytes();
+            let (bwt, pidx) = super::bwt(s);
+
+            // sorted suffixes, preceded by the empty one, with the
+            // sentinel left out of the result
+            let (_, suffixes) = sacabase::naive::sort(s).into_parts();
+            let mut expected = s[s.len().saturating_sub(1)..].to_vec();
+            let mut expected_pidx = s.len();
+            for (i, &p) in suffixes.iter().enumerate() {
+                if p == 0 {
+                    expected_pidx = i + 1;
+                } else {
+                    expected.push(s[p - 1]);
+                }
+            }
+
+            assert_eq!(bwt, expected, "bwt of {:?}", s);
+            assert_eq!(pidx as usize, expected_pidx, "pidx of {:?}", s);
+        }
+    }
+
+    #[test]
+    fn bwt_testdata() {
+        for s in &[
+            &include_bytes!("./testdata/fuzz1")[..],
+            &include_bytes!("./testdata/fuzz2")[..],
+            &include_bytes!("./testdata/fuzz3")[..],
+            &include_bytes!("./testdata/crash-cf8673530fdca659e0ddf070b4718b9c0bb504ec")[..],
+        ] {
+            let sa = super::sort(s);
+            let (_, sa) = sa.into_parts();
+            assert_eq!(super::bwt(s), super::bw_transform(s, &sa));
+        }
+    }
+
+    #[test]
+    fn inverse_bwt() {
+        for s in &[
+            &b""[..],
+            &b"a"[..],
+            &b"ab"[..],
+            &b"banana"[..],
+            &b"mmmmmm"[..],
+            r#"¯\_(ツ)_/¯"#.as_bytes(),
+            &include_bytes!("./testdata/fuzz1")[..],
+            &include_bytes!("./testdata/fuzz3")[..],
+        ] {
+            let (bwt, pidx) = super::bwt(s);
+            assert_eq!(&super::inverse_bwt(&bwt, pidx).unwrap()[..], *s);
+
+            let mut aux = vec![0; s.len()];
+            assert_eq!(
+                &super::inverse_bwt_with_aux(&bwt, pidx, &mut aux).unwrap()[..],
+                *s
+            );
+        }
+
+        use super::InvalidBwt;
+        assert_eq!(super::inverse_bwt(b"ab", 0), Err(InvalidBwt));
+        assert_eq!(super::inverse_bwt(b"ab", 3), Err(InvalidBwt));
+        assert_eq!(super::inverse_bwt(b"ab", 1), Err(InvalidBwt));
+        assert_eq!(
+            super::idx64::inverse_bwt_with_aux(b"ab", 1, &mut [0; 2]),
+            Err(InvalidBwt)
+        );
+    }
+
+    #[cfg(feature = "crosscheck")]
+    #[test]
+    fn sort_with_trace() {
+        let s = &include_bytes!("./testdata/fuzz3")[..];
+        let traces: Vec<Vec<u8>> = (0..4)
+            .map(|_| {
+                std::thread::spawn(move || {
+                    let mut trace = Vec::new();
+                    let sa = super::sort_with_trace(s, &mut trace).unwrap();
+                    sa.verify().unwrap();
+                    // not traced
+                    super::sort(s);
+                    trace
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .collect();
+        assert!(!traces[0].is_empty());
+        assert!(traces.iter().all(|t| *t == traces[0]));
+
+        let mut trace64 = Vec::new();
+        super::idx64::sort_with_trace(s, &mut trace64).unwrap();
+        assert_eq!(trace64, traces[0]);
+    }
+
+    #[test]
+    fn crosscheck_divergence() {
+        use super::crosscheck::first_divergence;
+
+        let c = "divsufsort\tmsg\tstart\n\
+                 sssort\tmsg\ta=1\n\
+                 sssort\tsa\tpre-mariachi\t3 1 2\n\
+                 trsort\tmsg\tdone\n";
+        assert_eq!(
+            first_divergence(c.as_bytes(), c.as_bytes(), 5).unwrap(),
+            None
+        );
+
+        let rust = c.replace("3 1 2", "3 2 1");
+        let d = first_divergence(c.as_bytes(), rust.as_bytes(), 1)
+            .unwrap()
+            .unwrap();
+        assert_eq!(d.record, 2);
+        assert_eq!(d.phase(), "sssort");
+        assert_eq!(d.context, vec!["sssort\tmsg\ta=1"]);
+        assert_eq!(d.first_differing_value(), Some((1, "1", "2")));
+        assert!(d.to_string().contains("in phase sssort"));
+
+        // one trace ends early
+        let rust = "divsufsort\tmsg\tstart\n";
+        let d = first_divergence(c.as_bytes(), rust.as_bytes(), 5)
+            .unwrap()
+            .unwrap();
+        assert_eq!(d.record, 1);
+        assert_eq!(d.right, None);
+        assert_eq!(d.first_differing_value(), None);
+    }
 }
@@ -0,0 +1,219 @@
+//! Sorts type B* buckets on several threads, like libdivsufsort does
+//! when built with OpenMP.
+
+use super::{common::*, sssort};
+use std::{
+    mem,
+    ops::{self, Index, IndexMut},
+    sync::Mutex,
+    thread,
+};
+
+/// Inputs shorter than this are not worth spawning threads for
+const PARALLEL_MIN_LEN: usize = 1 << 16;
+
+/// Number of threads to sort `n` bytes with: one per core, for
+/// large enough inputs.
+pub fn default_threads(n: usize) -> usize {
+    if n < PARALLEL_MIN_LEN {
+        return 1;
+    }
+    thread::available_parallelism().map_or(1, |t| t.get())
+}
+
+/// Sorts the type B* substrings, handing out buckets to up to `threads`
+/// threads. Returns false, without touching anything, if there is
+/// nothing to parallelize.
+///
+/// As in the OpenMP version, the threads share `PAb`, which `sssort` only
+/// reads, and split the unused middle of `SA` into one buffer each. Every
+/// thread sees them through `Regions`, with the bucket it's sorting.
+pub fn sssort_buckets(
+    T: &Text,
+    SA: &mut SuffixArray,
+    B: &mut BMixBucket,
+    m: Idx,
+    threads: usize,
+) -> bool {
+    let n = T.len();
+
+    // Same buckets, in the same order, as the serial loop
+    let mut buckets = Vec::new();
+    let mut c0 = ALPHABET_SIZE as Idx - 2;
+    let mut j = m;
+    while 0 < j {
+        let mut c1 = ALPHABET_SIZE as Idx - 1;
+        while c0 < c1 {
+            let i = B.bstar()[(c0, c1)];
+            if (1 < (j - i)) {
+                buckets.push((i, j));
+            }
+            j = i;
+            c1 -= 1;
+        }
+        c0 -= 1;
+    }
+
+    let threads = threads.min(buckets.len());
+    if threads <= 1 {
+        return false;
+    }
+
+    let (front, PAb) = SA.0.split_at_mut((n - m) as usize);
+    let PAb: &[Idx] = PAb;
+    let (mut rest, bufs) = front.split_at_mut(m as usize);
+    let mut work = Vec::with_capacity(buckets.len());
+    for &(i, j) in &buckets {
+        let (head, _) = mem::take(&mut rest).split_at_mut(j as usize);
+        let (head, bucket) = head.split_at_mut(i as usize);
+        rest = head;
+        work.push((i, bucket));
+    }
+    let work = Mutex::new(work.into_iter());
+
+    // Possibly empty: sssort borrows the end of the bucket then
+    let bufsize = (n - (2 * m)) / threads as Idx;
+    let mut bufs = bufs;
+    thread::scope(|s| {
+        for t in 0..threads {
+            let (buf, rest) = mem::take(&mut bufs).split_at_mut(bufsize as usize);
+            bufs = rest;
+            let buf_start = m + t as Idx * bufsize;
+            let work = &work;
+            s.spawn(move || loop {
+                let (first, bucket) = match work.lock().unwrap().next() {
+                    Some(work) => work,
+                    None => break,
+                };
+
+                let last = first + bucket.len() as Idx;
+                let lastsuffix = bucket[0] == (m - 1);
+                let mut regions = Regions {
+                    bucket,
+                    bucket_start: first,
+                    buf: &mut buf[..],
+                    buf_start,
+                    PAb,
+                    PAb_start: n - m,
+                };
+                sssort::sssort(
+                    T,
+                    &mut regions,
+                    SAPtr(n - m),
+                    SAPtr(first),
+                    SAPtr(last),
+                    SAPtr(buf_start),
+                    bufsize,
+                    2,
+                    n,
+                    lastsuffix,
+                );
+            });
+        }
+    });
+    true
+}
+
+/// The parts of `SA` a thread may use, indexed like the whole of it:
+/// the bucket it's sorting, its buffer, and `PAb`, read-only. Anything
+/// else is out of bounds.
+struct Regions<'a> {
+    bucket: &'a mut [Idx],
+    bucket_start: Idx,
+    buf: &'a mut [Idx],
+    buf_start: Idx,
+    PAb: &'a [Idx],
+    PAb_start: Idx,
+}
+
+impl<'a> Regions<'a> {
+    /// Offset of `i` in the region starting at `start`, if it's in there:
+    /// below `start`, it wraps around to something too large
+    #[inline(always)]
+    fn offset(i: Idx, start: Idx, len: usize) -> Option<usize> {
+        let j = (i - start) as usize;
+        if j < len {
+            Some(j)
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    fn get_mut(&mut self, i: Idx) -> &mut Idx {
+        if let Some(j) = Self::offset(i, self.bucket_start, self.bucket.len()) {
+            &mut self.bucket[j]
+        } else if let Some(j) = Self::offset(i, self.buf_start, self.buf.len()) {
+            &mut self.buf[j]
+        } else {
+            panic!("SA[{}] is not writable from this thread", i)
+        }
+    }
+}
+
+impl<'a> Index<Idx> for Regions<'a> {
+    type Output = Idx;
+
+    #[inline(always)]
+    fn index(&self, i: Idx) -> &Idx {
+        if let Some(j) = Self::offset(i, self.bucket_start, self.bucket.len()) {
+            &self.bucket[j]
+        } else if let Some(j) = Self::offset(i, self.PAb_start, self.PAb.len()) {
+            &self.PAb[j]
+        } else if let Some(j) = Self::offset(i, self.buf_start, self.buf.len()) {
+            &self.buf[j]
+        } else {
+            panic!("SA[{}] is not readable from this thread", i)
+        }
+    }
+}
+
+impl<'a> IndexMut<Idx> for Regions<'a> {
+    #[inline(always)]
+    fn index_mut(&mut self, i: Idx) -> &mut Idx {
+        self.get_mut(i)
+    }
+}
+
+impl<'a> Index<SAPtr> for Regions<'a> {
+    type Output = Idx;
+
+    #[inline(always)]
+    fn index(&self, i: SAPtr) -> &Idx {
+        &self[i.0]
+    }
+}
+
+impl<'a> IndexMut<SAPtr> for Regions<'a> {
+    #[inline(always)]
+    fn index_mut(&mut self, i: SAPtr) -> &mut Idx {
+        self.get_mut(i.0)
+    }
+}
+
+impl<'a> SortArray for Regions<'a> {
+    #[inline(always)]
+    fn swap<A: Into<Idx>, B: Into<Idx>>(&mut self, a: A, b: B) {
+        let (a, b) = (a.into(), b.into());
+        let t = self[a];
+        self[a] = self[b];
+        self[b] = t;
+    }
+
+    fn range<'b, I: Into<Idx>>(&'b mut self, range: ops::Range<I>) -> SuffixArray<'b> {
+        let (start, end) = (range.start.into(), range.end.into());
+        for (region, region_start) in [
+            (&mut *self.bucket, self.bucket_start),
+            (&mut *self.buf, self.buf_start),
+        ] {
+            if region_start <= start && end <= region_start + region.len() as Idx {
+                let (start, end) = (
+                    (start - region_start) as usize,
+                    (end - region_start) as usize,
+                );
+                return SuffixArray(&mut region[start..end]);
+            }
+        }
+        panic!("SA[{}..{}] is not writable from this thread", start, end)
+    }
+}
@@ -1,5 +1,6 @@
-use crate::{common::*, crosscheck, crosscheck::*, SA_dump};
-use std::{cmp, default::Default, mem};
+use super::common::*;
+use crate::{crosscheck, crosscheck::*, SA_dump};
+use std::{cmp, default::Default, mem, ops::Index};
 
 //--------------------
 // Private functions
@@ -99,14 +100,11 @@ pub fn ss_isqrt<X: Into<Idx>>(x: X) -> Idx {
 
 /// Compare two suffixes
 #[inline(always)]
-pub fn ss_compare(
-    T: &Text,
-    SAp1: &SuffixArray,
-    p1: SAPtr,
-    SAp2: &SuffixArray,
-    p2: SAPtr,
-    depth: Idx,
-) -> Idx {
+pub fn ss_compare<S1, S2>(T: &Text, SAp1: &S1, p1: SAPtr, SAp2: &S2, p2: SAPtr, depth: Idx) -> Idx
+where
+    S1: Index<SAPtr, Output = Idx> + ?Sized,
+    S2: Index<SAPtr, Output = Idx> + ?Sized,
+{
     let mut U1 = depth + SAp1[p1];
     let mut U2 = depth + SAp2[p2];
     let U1n = SAp1[p1 + 1] + 2;
@@ -136,9 +134,9 @@ pub fn ss_compare(
 //------------------------------------------------------------------------------
 
 /// Insertionsort for small size groups
-pub fn ss_insertionsort(
+pub fn ss_insertionsort<S: SortArray>(
     T: &Text,
-    SA: &mut SuffixArray,
+    SA: &mut S,
     PA: SAPtr,
     first: SAPtr,
     last: SAPtr,
@@ -195,11 +193,11 @@ pub fn ss_insertionsort(
 //------------------------------------------------------------------------------
 
 // TODO: document?
-pub fn ss_fixdown(
+pub fn ss_fixdown<S: SortArray>(
     T: &Text,
     Td: Idx,
     PA: SAPtr,
-    SA_top: &mut SuffixArray,
+    SA_top: &mut S,
     first: SAPtr,
     mut i: Idx,
     size: Idx,
@@ -260,10 +258,10 @@ pub fn ss_fixdown(
 }
 
 /// Simple top-down heapsort.
-pub fn ss_heapsort(
+pub fn ss_heapsort<S: SortArray>(
     T: &Text,
     Td: Idx,
-    SA_top: &mut SuffixArray,
+    SA_top: &mut S,
     PA: SAPtr,
     first: SAPtr,
     size: Idx,
@@ -323,10 +321,10 @@ pub fn ss_heapsort(
 
 /// Returns the median of three elements
 #[inline(always)]
-pub fn ss_median3(
+pub fn ss_median3<S: SortArray>(
     T: &Text,
     Td: Idx,
-    SA: &SuffixArray,
+    SA: &S,
     PA: SAPtr,
     mut v1: SAPtr,
     mut v2: SAPtr,
@@ -355,10 +353,10 @@ pub fn ss_median3(
 
 /// Returns the median of five elements
 #[inline(always)]
-pub fn ss_median5(
+pub fn ss_median5<S: SortArray>(
     T: &Text,
     Td: Idx,
-    SA: &SuffixArray,
+    SA: &S,
     PA: SAPtr,
     mut v1: SAPtr,
     mut v2: SAPtr,
@@ -398,10 +396,10 @@ pub fn ss_median5(
 
 /// Returns the pivot element
 #[inline(always)]
-pub fn ss_pivot(
+pub fn ss_pivot<S: SortArray>(
     T: &Text,
     Td: Idx,
-    SA: &SuffixArray,
+    SA: &S,
     PA: SAPtr,
     mut first: SAPtr,
     mut last: SAPtr,
@@ -438,8 +436,8 @@ pub fn ss_pivot(
 
 /// Binary partition for substrings.
 #[inline(always)]
-pub fn ss_partition(
-    SA: &mut SuffixArray,
+pub fn ss_partition<S: SortArray>(
+    SA: &mut S,
     PA: SAPtr,
     first: SAPtr,
     last: SAPtr,
@@ -508,9 +506,9 @@ pub fn ss_partition(
 }
 
 /// Multikey introsort for medium size groups
-pub fn ss_mintrosort(
+pub fn ss_mintrosort<S: SortArray>(
     T: &Text,
-    SA: &mut SuffixArray,
+    SA: &mut S,
     PA: SAPtr,
     mut first: SAPtr,
     mut last: SAPtr,
@@ -819,14 +817,14 @@ pub fn ss_mintrosort(
 //------------------------------------------------------------------------------
 
 #[inline(always)]
-pub fn ss_blockswap(SA: &mut SuffixArray, a: SAPtr, b: SAPtr, mut n: Idx) {
+pub fn ss_blockswap<S: SortArray>(SA: &mut S, a: SAPtr, b: SAPtr, mut n: Idx) {
     for i in 0..n {
         SA.swap(a + i, b + i);
     }
 }
 
 #[inline(always)]
-pub fn ss_rotate(SA: &mut SuffixArray, mut first: SAPtr, middle: SAPtr, mut last: SAPtr) {
+pub fn ss_rotate<S: SortArray>(SA: &mut S, mut first: SAPtr, middle: SAPtr, mut last: SAPtr) {
     let mut a: SAPtr;
     let mut b: SAPtr;
     let mut t: Idx;
@@ -903,9 +901,9 @@ pub fn ss_rotate(SA: &mut SuffixArray, mut first: SAPtr, middle: SAPtr, mut last
 
 //------------------------------------------------------------------------------
 
-pub fn ss_inplacemerge(
+pub fn ss_inplacemerge<S: SortArray>(
     T: &Text,
-    SA: &mut SuffixArray,
+    SA: &mut S,
     PA: SAPtr,
     mut first: SAPtr,
     mut middle: SAPtr,
@@ -1000,9 +998,9 @@ pub fn ss_inplacemerge(
 //------------------------------------------------------------------------------
 
 /// Merge-forward with internal buffer
-pub fn ss_mergeforward(
+pub fn ss_mergeforward<S: SortArray>(
     T: &Text,
-    SA: &mut SuffixArray,
+    SA: &mut S,
     PA: SAPtr,
     first: SAPtr,
     middle: SAPtr,
@@ -1119,9 +1117,9 @@ pub fn ss_mergeforward(
 }
 
 /// Merge-backward with internal buffer
-pub fn ss_mergebackward(
+pub fn ss_mergebackward<S: SortArray>(
     T: &Text,
-    SA: &mut SuffixArray,
+    SA: &mut S,
     PA: SAPtr,
     first: SAPtr,
     middle: SAPtr,
@@ -1300,8 +1298,9 @@ pub fn ss_mergebackward(
     }
 }
 
-const MERGE_STACK_SIZE: usize = 32;
+const MERGE_STACK_SIZE: usize = SS_SMERGE_STACKSIZE;
 
+#[derive(Clone, Copy)]
 struct MergeStackItem {
     a: SAPtr,
     b: SAPtr,
@@ -1328,14 +1327,14 @@ struct MergeStack {
 impl MergeStack {
     fn new() -> Self {
         Self {
-            items: Default::default(),
+            items: [MergeStackItem::default(); MERGE_STACK_SIZE],
             size: 0,
         }
     }
 
     #[inline(always)]
     fn push(&mut self, a: SAPtr, b: SAPtr, c: SAPtr, d: Idx) {
-        assert!(self.size < STACK_SIZE);
+        assert!(self.size < MERGE_STACK_SIZE);
         self.items[self.size].a = a;
         self.items[self.size].b = b;
         self.items[self.size].c = c;
@@ -1360,9 +1359,9 @@ impl MergeStack {
 }
 
 /// D&C based merge
-pub fn ss_swapmerge(
+pub fn ss_swapmerge<S: SortArray>(
     T: &Text,
-    SA: &mut SuffixArray,
+    SA: &mut S,
     PA: SAPtr,
     mut first: SAPtr,
     mut middle: SAPtr,
@@ -1628,9 +1627,9 @@ impl Stack {
 //--------------------
 
 /// Substring sort
-pub fn sssort(
+pub fn sssort<S: SortArray>(
     T: &Text,
-    SA: &mut SuffixArray,
+    SA: &mut S,
     PA: SAPtr,
     mut first: SAPtr,
     last: SAPtr,
@@ -1,4 +1,5 @@
-use crate::{common::*, crosscheck, crosscheck::*, SA_dump};
+use super::common::*;
+use crate::{crosscheck, crosscheck::*, SA_dump};
 use std::mem;
 
 //--------------------
@@ -18,9 +19,27 @@ const lg_table: [Idx; 256] = [
 ];
 
 #[inline(always)]
-#[allow(overflowing_literals)]
+#[allow(overflowing_literals, clippy::unnecessary_cast)]
 pub fn tr_ilg<N: Into<Idx>>(n: N) -> Idx {
     let n = n.into();
+    // only taken by the 64-bit build
+    let wide = n as i64;
+    if (wide >> 32) > 0 {
+        return if (wide >> 48) > 0 {
+            if (wide >> 56) > 0 {
+                56 + lg_table[((wide >> 56) & 0xff) as usize]
+            } else {
+                48 + lg_table[((wide >> 48) & 0xff) as usize]
+            }
+        } else {
+            if (wide >> 40) > 0 {
+                40 + lg_table[((wide >> 40) & 0xff) as usize]
+            } else {
+                32 + lg_table[((wide >> 32) & 0xff) as usize]
+            }
+        };
+    }
+
     if (n & 0xffff_0000) > 0 {
         if (n & 0xff00_0000) > 0 {
             24 + lg_table[((n >> 24) & 0xff) as usize]
@@ -39,8 +58,9 @@ pub fn tr_ilg<N: Into<Idx>>(n: N) -> Idx {
 //------------------------------------------------------------------------------
 
 use std::default::Default;
-const STACK_SIZE: usize = 64;
+const STACK_SIZE: usize = TR_STACKSIZE;
 
+#[derive(Clone, Copy)]
 struct StackItem {
     a: SAPtr,
     b: SAPtr,
@@ -69,72 +89,7 @@ struct Stack {
 impl Stack {
     fn new() -> Self {
         Self {
-            items: [
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-            ],
+            items: [StackItem::default(); STACK_SIZE],
             size: 0,
         }
     }
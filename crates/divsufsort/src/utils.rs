@@ -0,0 +1,129 @@
+use super::common::*;
+use crate::InvalidBwt;
+
+/// Burrows-Wheeler transform of `T` into `U`, using its suffix array `SA`.
+/// Returns the primary index.
+pub fn bw_transform(T: &[Char], U: &mut [Char], SA: &[Idx]) -> Idx {
+    assert_eq!(T.len(), U.len(), "text and output should have same len");
+    assert_eq!(
+        T.len(),
+        SA.len(),
+        "text and suffix array should have same len"
+    );
+
+    let n = T.len();
+    if n <= 1 {
+        if n == 1 {
+            U[0] = T[0];
+        }
+        return n as Idx;
+    }
+
+    U[0] = T[n - 1];
+    let mut i = 0;
+    while SA[i] != 0 {
+        U[i + 1] = T[SA[i] as usize - 1];
+        i += 1;
+    }
+    let idx = i + 1;
+    for i in idx..n {
+        U[i] = T[SA[i] as usize - 1];
+    }
+    idx as Idx
+}
+
+/// Binary search for inverse bwt.
+fn binarysearch_lower(A: &[Idx], mut size: Idx, value: Idx) -> Idx {
+    let mut i: Idx = 0;
+    let mut half = size >> 1;
+    while 0 < size {
+        if A[(i + half) as usize] < value {
+            i += half + 1;
+            half -= (size & 1) ^ 1;
+        }
+
+        // iter
+        size = half;
+        half >>= 1;
+    }
+    i
+}
+
+/// Inverse Burrows-Wheeler transform of `T` into `U`, given the primary
+/// index `idx` returned by the forward transform.
+/// `A` is used as scratch space if given, otherwise it's allocated.
+/// Fails if `T` isn't the transform of any text with that primary index.
+pub fn inverse_bw_transform(
+    T: &[Char],
+    U: &mut [Char],
+    A: Option<&mut [Idx]>,
+    idx: Idx,
+) -> Result<(), InvalidBwt> {
+    assert_eq!(T.len(), U.len(), "text and output should have same len");
+    let n = T.len() as Idx;
+    if (idx < 0) || (n < idx) || ((0 < n) && (idx == 0)) {
+        return Err(InvalidBwt);
+    }
+
+    if n <= 1 {
+        // the C version leaves `U` untouched here
+        U.copy_from_slice(T);
+        return Ok(());
+    }
+
+    let mut owned: Vec<Idx>;
+    let B = match A {
+        Some(A) => {
+            assert_eq!(
+                T.len(),
+                A.len(),
+                "text and temporary array should have same len"
+            );
+            A
+        }
+        None => {
+            owned = vec![0; n as usize];
+            &mut owned[..]
+        }
+    };
+
+    let mut C = [0 as Idx; ALPHABET_SIZE];
+    let mut D = [0 as Char; ALPHABET_SIZE];
+
+    // Inverse BW transform.
+    for &c in T {
+        C[c as usize] += 1;
+    }
+    let mut d = 0;
+    {
+        let mut i = 0;
+        for (c, Cc) in C.iter_mut().enumerate() {
+            let p = *Cc;
+            if 0 < p {
+                *Cc = i;
+                D[d] = c as Char;
+                d += 1;
+                i += p;
+            }
+        }
+    }
+    for i in 0..n {
+        let c = T[i as usize] as usize;
+        B[C[c] as usize] = if i < idx { i } else { i + 1 };
+        C[c] += 1;
+    }
+    for c in 0..d {
+        C[c] = C[D[c] as usize];
+    }
+    let mut p = idx;
+    for u in U.iter_mut() {
+        // the walk only comes back to 0 after the last byte, unless
+        // `T` and `idx` don't go together
+        if p == 0 {
+            return Err(InvalidBwt);
+        }
+        *u = D[binarysearch_lower(&C, d as Idx, p) as usize];
+        p = B[(p - 1) as usize];
+    }
+    Ok(())
+}
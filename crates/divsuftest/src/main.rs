@@ -1,12 +1,49 @@
 use failure::Fallible;
+use sacabase::{DynSaca, Saca};
 use size_format::SizeFormatterBinary;
-use std::{env, io::Write, process, time::Instant};
+use std::{io::Write, process};
 
 struct Args {
+    #[allow(dead_code)]
     partitions: u32,
+    algorithm: Option<String>,
     free: Vec<String>,
 }
 
+/// SA-K, from the `suffix_array` crate
+struct SacaK;
+
+impl Saca for SacaK {
+    type Index = u32;
+
+    fn name(&self) -> &'static str {
+        "saca-k"
+    }
+
+    fn max_len(&self) -> usize {
+        u32::MAX as usize - 1
+    }
+
+    fn sort_in_place(&self, text: &[u8], sa: &mut [u32]) {
+        let (_, full) = suffix_array::SuffixArray::new(text).into_parts();
+        // skip the empty suffix
+        sa.copy_from_slice(&full[1..]);
+    }
+}
+
+/// All the algorithms divsuftest knows about
+fn algorithms() -> Vec<Box<dyn DynSaca>> {
+    vec![
+        Box::new(cdivsufsort::CDivSufSort),
+        Box::new(divsufsort::DivSufSort),
+        Box::new(cdivsufsort::idx64::CDivSufSort),
+        Box::new(divsufsort::idx64::DivSufSort),
+        Box::new(dc3::Dc3),
+        Box::new(sais::Sais),
+        Box::new(SacaK),
+    ]
+}
+
 enum Command {
     Crosscheck,
     Bench,
@@ -30,13 +67,14 @@ fn main() -> Fallible<()> {
     let mut args = pico_args::Arguments::from_env();
     let args = Args {
         partitions: args.opt_value_from_str("--partitions")?.unwrap_or(1),
+        algorithm: args.opt_value_from_str("--algorithm")?,
         free: args.free()?,
     };
 
     if args.free.is_empty() {
         usage();
     }
-    let cmd = Command::parse(&args.free.get(0).unwrap_or_else(|| {
+    let cmd = Command::parse(args.free.first().unwrap_or_else(|| {
         usage();
         unreachable!();
     }))
@@ -70,61 +108,117 @@ fn main() -> Fallible<()> {
             }
 
             #[cfg(feature = "crosscheck")]
-            command_crosscheck(input)?;
+            command_crosscheck(input);
         }
-        Command::Bench => command_bench(input)?,
-        Command::Run => command_run(input)?,
+        Command::Bench => command_bench(input, &select(args.algorithm.as_deref()))?,
+        Command::Run => command_run(
+            input,
+            &*select(Some(args.algorithm.as_deref().unwrap_or("divsufsort")))
+                .pop()
+                .unwrap(),
+        )?,
     }
     Ok(())
 }
 
 fn usage() {
-    println!("Usage: divsuftest bench|crosscheck|run INPUT [LENGTH]");
+    println!("Usage: divsuftest bench|crosscheck|run [--algorithm NAME] INPUT [LENGTH]");
+    let names: Vec<_> = algorithms().iter().map(|a| a.name()).collect();
+    println!("Algorithms: {}", names.join(", "));
     process::exit(1);
 }
 
+/// Returns the algorithm called `name`, or all of them
+fn select(name: Option<&str>) -> Vec<Box<dyn DynSaca>> {
+    let all = algorithms();
+    match name {
+        None => all,
+        Some(name) => {
+            let selected: Vec<_> = all.into_iter().filter(|a| a.name() == name).collect();
+            if selected.is_empty() {
+                println!("Unknown algorithm {:?}", name);
+                usage();
+            }
+            selected
+        }
+    }
+}
+
+/// Number of matching trace records to show before a divergence
+#[cfg(feature = "crosscheck")]
+const CROSSCHECK_CONTEXT: usize = 5;
+
 #[cfg(feature = "crosscheck")]
 fn command_crosscheck(input: &[u8]) {
+    use std::{fs::File, io::BufWriter};
+
     println!("Cross-checking!");
     std::fs::create_dir_all("crosscheck").unwrap();
+    let create = |path| BufWriter::new(File::create(path).unwrap());
 
     {
         println!("Running C version...");
-        let sa = cdivsufsort::sort(input);
-        unsafe {
-            cdivsufsort::dss_flush();
-        }
+        let mut trace = create("crosscheck/c");
+        let sa = cdivsufsort::sort_with_trace(input, &mut trace).unwrap();
+        trace.flush().unwrap();
         println!("Verifying C result...");
         sa.verify().expect("cdivsufsort should sort all suffixes");
     }
 
+    let mut trace = create("crosscheck/rust");
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        println!("Running Rust version...");
+        let sa = divsufsort::sort_with_trace(input, &mut trace).unwrap();
+
+        println!("Verifying Rust result...");
+        sa.verify().expect("divsufsort should sort all suffixes");
+    }));
+    trace.flush().unwrap();
+    drop(trace);
+
+    // Compare traces even if the Rust version panicked, that's when
+    // they're the most useful.
+    println!("Comparing traces...");
+    let open = |path| std::io::BufReader::new(std::fs::File::open(path).unwrap());
+    match divsufsort::crosscheck::first_divergence(
+        open("crosscheck/c"),
+        open("crosscheck/rust"),
+        CROSSCHECK_CONTEXT,
+    )
+    .unwrap()
     {
-        let res = std::panic::catch_unwind(|| {
-            println!("Running Rust version...");
-            std::thread::spawn(|| loop {
-                std::thread::sleep(std::time::Duration::from_millis(500));
-                divsufsort::crosscheck::flush();
-            });
-
-            let sa = divsufsort::sort(input);
-
-            println!("Verifying Rust result...");
-            sa.verify().expect("cdivsufsort should sort all suffixes");
-        });
-        divsufsort::crosscheck::flush();
-        res.unwrap()
-    };
+        None => println!("Traces are identical"),
+        Some(divergence) => {
+            println!("Traces diverge (< C, > Rust):");
+            println!("{}", divergence);
+        }
+    }
+    if let Err(e) = res {
+        std::panic::resume_unwind(e);
+    }
+
+    {
+        println!("Running DC3...");
+        let sa = dc3::sort(input);
+        println!("Verifying DC3 result...");
+        sa.verify().expect("dc3 should sort all suffixes");
+        let (_, sa) = sa.into_parts();
+        let (_, c_sa) = cdivsufsort::sort(input).into_parts();
+        assert!(
+            sa.iter().zip(c_sa.iter()).all(|(&a, &b)| a == b as usize),
+            "dc3 and cdivsufsort should agree"
+        );
+    }
 }
 
-fn command_run(input: &[u8]) -> Fallible<()> {
-    let before = Instant::now();
-    divsufsort::sort(input);
-    println!("Done in {:?}", before.elapsed());
+fn command_run(input: &[u8], saca: &dyn DynSaca) -> Fallible<()> {
+    println!("Running {} ({} indices)", saca.name(), saca.index_type());
+    println!("Done in {:?}", saca.sort_timed(input));
 
     Ok(())
 }
 
-fn command_bench(input: &[u8]) -> Fallible<()> {
+fn command_bench(input: &[u8], algorithms: &[Box<dyn DynSaca>]) -> Fallible<()> {
     #[cfg(debug_assertions)]
     {
         println!("==========================================");
@@ -146,26 +240,17 @@ fn command_bench(input: &[u8]) -> Fallible<()> {
     };
 
     let mut datapoints = Vec::new();
-    let mut measure = |name: &'static str, f: &dyn Fn()| {
-        print!(".");
-        flush();
-        let before = Instant::now();
-        f();
-        datapoints.push((name, before.elapsed()))
-    };
 
     print!("measuring");
     flush();
 
-    measure("c-divsufsort", &|| {
-        cdivsufsort::sort(input);
-    });
-    measure("divsufsort", &|| {
-        divsufsort::sort(input);
-    });
-    measure("saca-k", &|| {
-        suffix_array::SuffixArray::new(input);
-    });
+    for saca in algorithms {
+        if input.len() <= saca.max_len() {
+            print!(".");
+            flush();
+            datapoints.push((saca.name(), saca.sort_timed(input)));
+        }
+    }
 
     println!("done!");
 
@@ -0,0 +1,280 @@
+//! An FM-index: backward search over the Burrows-Wheeler transform,
+//! with a sampled suffix array for locating occurrences.
+//!
+//! Rows of the BW matrix are the suffixes of the text in sorted order,
+//! preceded by the empty suffix (row 0), so row `r > 0` stands for
+//! suffix array entry `r - 1`. The last column `L` is `divsufsort::bwt`'s
+//! output, with the sentinel reinserted at the primary index.
+
+mod bits;
+mod wavelet;
+
+use bits::RankBits;
+use std::ops::Range;
+use wavelet::WaveletMatrix;
+
+/// Default distance between sampled text positions
+pub const DEFAULT_SAMPLE_RATE: usize = 32;
+
+/// An FM-index over a byte string
+pub struct FmIndex {
+    /// `L`, without the sentinel
+    bwt: WaveletMatrix,
+    /// Row whose `L` is the sentinel (the row of suffix 0)
+    pidx: usize,
+    /// `c[x]` is the first row of suffixes starting with `x`
+    c: Vec<usize>,
+    /// Rows whose suffix array entry is sampled
+    sampled: RankBits,
+    /// Sampled suffix array entries, in row order
+    samples: Vec<u32>,
+    sample_rate: usize,
+}
+
+impl FmIndex {
+    /// Builds the FM-index of `text`, with the default sample rate
+    pub fn new(text: &[u8]) -> Self {
+        Self::with_sample_rate(text, DEFAULT_SAMPLE_RATE)
+    }
+
+    /// Builds the FM-index of `text`, keeping the suffix array entries
+    /// of text positions that are multiples of `sample_rate`. Lower rates
+    /// make `locate` faster, and the index larger.
+    pub fn with_sample_rate(text: &[u8], sample_rate: usize) -> Self {
+        assert!(sample_rate > 0, "sample rate should be positive");
+        assert!(
+            text.len() < u32::MAX as usize,
+            "text too large, should not exceed {} bytes",
+            u32::MAX - 1
+        );
+
+        let (bwt, pidx) = divsufsort::bwt(text);
+        let pidx = pidx as usize;
+
+        let mut c = vec![0; 257];
+        for &x in text {
+            c[x as usize + 1] += 1;
+        }
+        // the empty suffix comes first
+        c[0] = 1;
+        for x in 1..c.len() {
+            c[x] += c[x - 1];
+        }
+
+        let mut index = Self {
+            bwt: WaveletMatrix::new(&bwt),
+            pidx,
+            c,
+            sampled: RankBits::new(0, |_| false),
+            samples: Vec::new(),
+            sample_rate,
+        };
+        drop(bwt);
+
+        // Walk the text backwards from the empty suffix, to find the row
+        // of every sampled position.
+        let n = text.len();
+        let mut rows = Vec::with_capacity(n / sample_rate + 1);
+        let mut row = 0;
+        for pos in (0..n).rev() {
+            row = index.lf(row);
+            if pos % sample_rate == 0 {
+                rows.push((row, pos as u32));
+            }
+        }
+
+        let mut is_sampled = vec![false; n + 1];
+        for &(row, _) in &rows {
+            is_sampled[row] = true;
+        }
+        index.sampled = RankBits::new(n + 1, |i| is_sampled[i]);
+        index.samples = vec![0; rows.len()];
+        for (row, pos) in rows {
+            index.samples[index.sampled.rank1(row)] = pos;
+        }
+        index
+    }
+
+    /// Length of the indexed text
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.bwt.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline(always)]
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    /// Number of occurrences of `x` in `L[0..row]`
+    #[inline(always)]
+    fn occ(&self, x: u8, row: usize) -> usize {
+        // skip the sentinel
+        let i = if row > self.pidx { row - 1 } else { row };
+        self.bwt.rank(x, i)
+    }
+
+    /// LF mapping: the row of the suffix one position before `row`'s
+    #[inline(always)]
+    fn lf(&self, row: usize) -> usize {
+        if row == self.pidx {
+            // suffix 0 is preceded by the empty suffix
+            return 0;
+        }
+        let i = if row > self.pidx { row - 1 } else { row };
+        let (x, rank) = self.bwt.access_rank(i);
+        self.c[x as usize] + rank
+    }
+
+    /// Rows of suffixes starting with `pattern`
+    fn rows(&self, pattern: &[u8]) -> Range<usize> {
+        if pattern.is_empty() {
+            // every suffix but the empty one
+            return 1..self.len() + 1;
+        }
+
+        // the empty suffix can be extended, so it's a candidate too
+        let mut sp = 0;
+        let mut ep = self.len() + 1;
+        for &x in pattern.iter().rev() {
+            if sp >= ep {
+                break;
+            }
+            sp = self.c[x as usize] + self.occ(x, sp);
+            ep = self.c[x as usize] + self.occ(x, ep);
+        }
+        sp..ep.max(sp)
+    }
+
+    /// Returns the interval of the suffix array whose suffixes start
+    /// with `pattern`, same as `sacabase::SuffixArray::range`. The interval
+    /// is empty if `pattern` does not occur, but may start elsewhere.
+    pub fn range(&self, pattern: &[u8]) -> Range<usize> {
+        let rows = self.rows(pattern);
+        rows.start - 1..rows.end - 1
+    }
+
+    /// Returns the number of occurrences of `pattern` in the text
+    pub fn count(&self, pattern: &[u8]) -> usize {
+        self.rows(pattern).len()
+    }
+
+    /// Returns the positions of all occurrences of `pattern`, in suffix
+    /// array order
+    pub fn locate(&self, pattern: &[u8]) -> Locations<'_> {
+        Locations {
+            index: self,
+            rows: self.rows(pattern),
+        }
+    }
+
+    /// Text position of the suffix at `row`
+    fn position(&self, mut row: usize) -> usize {
+        let mut steps = 0;
+        while !self.sampled.get(row) {
+            row = self.lf(row);
+            steps += 1;
+        }
+        self.samples[self.sampled.rank1(row)] as usize + steps
+    }
+
+    /// Heap memory used, in bytes
+    pub fn heap_size(&self) -> usize {
+        self.bwt.heap_size() + self.c.len() * 8 + self.sampled.heap_size() + self.samples.len() * 4
+    }
+}
+
+/// Positions of occurrences, returned by `FmIndex::locate`
+pub struct Locations<'a> {
+    index: &'a FmIndex,
+    rows: Range<usize>,
+}
+
+impl<'a> Iterator for Locations<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.rows.next().map(|row| self.index.position(row))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+}
+
+impl<'a> DoubleEndedIterator for Locations<'a> {
+    fn next_back(&mut self) -> Option<usize> {
+        self.rows.next_back().map(|row| self.index.position(row))
+    }
+}
+
+impl<'a> ExactSizeIterator for Locations<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(text: &[u8], patterns: &[&[u8]], sample_rate: usize) {
+        let index = FmIndex::with_sample_rate(text, sample_rate);
+        let sa = divsufsort::sort(text);
+        assert_eq!(index.len(), text.len());
+
+        for pattern in patterns {
+            assert_eq!(index.count(pattern), sa.count(pattern), "{:?}", pattern);
+            if index.count(pattern) > 0 {
+                assert_eq!(index.range(pattern), sa.range(pattern), "{:?}", pattern);
+            }
+            assert!(
+                index.locate(pattern).eq(sa.find_all(pattern)),
+                "locate {:?}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn small() {
+        let patterns: &[&[u8]] = &[
+            b"", b"a", b"an", b"ana", b"nan", b"banana", b"bananas", b"x",
+        ];
+        for text in &["", "a", "b", "ab", "aaaa", "banana", "ananas and bananas"] {
+            for &rate in &[1, 2, 3, 32] {
+                check(text.as_bytes(), patterns, rate);
+            }
+        }
+    }
+
+    #[test]
+    fn testdata() {
+        for text in &[
+            &include_bytes!("../../divsufsort/src/testdata/fuzz1")[..],
+            &include_bytes!("../../divsufsort/src/testdata/fuzz3")[..],
+        ] {
+            let mut patterns: Vec<&[u8]> = vec![b"", &text[..1], &text[text.len() - 3..]];
+            for i in (0..text.len() - 8).step_by(97) {
+                patterns.push(&text[i..i + 1]);
+                patterns.push(&text[i..i + 4]);
+                patterns.push(&text[i..i + 8]);
+            }
+            check(text, &patterns, 7);
+        }
+    }
+
+    #[test]
+    fn compressed() {
+        let text = sacabase::naive::Xorshift(0x2545_f491).text(1 << 16, b"acgt");
+
+        let index = FmIndex::new(&text);
+        assert!(index.heap_size() < text.len() * 2);
+        check(
+            &text,
+            &[b"acgt", b"gattaca", &text[1000..1020]],
+            DEFAULT_SAMPLE_RATE,
+        );
+    }
+}
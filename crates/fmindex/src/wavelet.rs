@@ -0,0 +1,81 @@
+use crate::bits::RankBits;
+
+/// Bits per symbol
+const LEVELS: usize = 8;
+
+/// A wavelet matrix over bytes: O(log σ) access and rank, in about
+/// n log σ bits (plus rank support).
+///
+/// Level `l` holds bit `7 - l` of every symbol, after the symbols have
+/// been stably partitioned by their `l` most significant bits, zeros first.
+pub struct WaveletMatrix {
+    levels: Vec<RankBits>,
+    /// Number of zeros on every level
+    zeros: Vec<usize>,
+}
+
+impl WaveletMatrix {
+    pub fn new(symbols: &[u8]) -> Self {
+        let mut levels = Vec::with_capacity(LEVELS);
+        let mut zeros = Vec::with_capacity(LEVELS);
+        let mut cur = symbols.to_vec();
+        let mut next = Vec::with_capacity(cur.len());
+
+        for l in 0..LEVELS {
+            let shift = LEVELS - 1 - l;
+            levels.push(RankBits::new(cur.len(), |i| cur[i] >> shift & 1 == 1));
+
+            next.clear();
+            next.extend(cur.iter().filter(|&&c| c >> shift & 1 == 0));
+            zeros.push(next.len());
+            next.extend(cur.iter().filter(|&&c| c >> shift & 1 == 1));
+            std::mem::swap(&mut cur, &mut next);
+        }
+        Self { levels, zeros }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    /// Returns the `i`-th symbol, and the number of times it occurs in `0..i`
+    pub fn access_rank(&self, mut i: usize) -> (u8, usize) {
+        // `start` tracks where the symbol's range begins on every level
+        let mut start = 0;
+        let mut c = 0_u8;
+        for (level, &zeros) in self.levels.iter().zip(&self.zeros) {
+            if level.get(i) {
+                c = c << 1 | 1;
+                i = zeros + level.rank1(i);
+                start = zeros + level.rank1(start);
+            } else {
+                c <<= 1;
+                i = level.rank0(i);
+                start = level.rank0(start);
+            }
+        }
+        (c, i - start)
+    }
+
+    /// Number of occurrences of `c` in `0..i`
+    pub fn rank(&self, c: u8, i: usize) -> usize {
+        let mut start = 0;
+        let mut end = i;
+        for (l, (level, &zeros)) in self.levels.iter().zip(&self.zeros).enumerate() {
+            if c >> (LEVELS - 1 - l) & 1 == 1 {
+                start = zeros + level.rank1(start);
+                end = zeros + level.rank1(end);
+            } else {
+                start = level.rank0(start);
+                end = level.rank0(end);
+            }
+        }
+        end - start
+    }
+
+    /// Heap memory used, in bytes
+    pub fn heap_size(&self) -> usize {
+        self.levels.iter().map(|l| l.heap_size()).sum::<usize>() + self.zeros.len() * 8
+    }
+}
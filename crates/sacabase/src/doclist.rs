@@ -0,0 +1,267 @@
+//! Document listing for generalized suffix arrays, after Muthukrishnan's
+//! "Efficient algorithms for document retrieval problems" (2002): every
+//! distinct document containing a pattern is reported once, in time
+//! proportional to the number of documents rather than of occurrences.
+
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::ops::Range;
+
+/// Rows per block of the range minimum query structure. With 64, the
+/// sparse table over blocks always has fewer than n entries.
+const BLOCK: usize = 64;
+
+/// The side tables for document listing.
+///
+/// `docs[i]` is the document of suffix `sa[i]`, and `prev[i]` is one past
+/// the last row before `i` with the same document, or 0 if there is none.
+/// A range `l..r` of rows holds one first occurrence for every distinct
+/// document, at exactly the rows where `prev[i] <= l`, which are found by
+/// range minimum queries. `rows` holds the rows of every document, in
+/// order, to count occurrences with two binary searches.
+///
+/// All of it takes about 4n words, `docs`, `prev` and `rows` being one
+/// each: range minimum queries only keep the minimum of every block of
+/// `BLOCK` rows, and a sparse table over those, which is under n words.
+pub struct DocumentTable<Index> {
+    docs: Vec<Index>,
+    prev: Vec<Index>,
+    /// `rmq[k][b]` is the row of the minimum of `prev` over blocks
+    /// `b..b + 2^k`
+    rmq: Vec<Vec<Index>>,
+    rows: Vec<Index>,
+    /// Start of every document in `rows`, followed by `rows.len()`
+    starts: Vec<usize>,
+}
+
+impl<Index> DocumentTable<Index>
+where
+    Index: ToPrimitive + FromPrimitive,
+{
+    /// Builds the tables from the document of every row, in O(n)
+    pub fn new(docs: Vec<Index>, num_docs: usize) -> Self {
+        let n = docs.len();
+        let idx = |x: usize| Index::from_usize(x).unwrap();
+
+        let mut last = vec![0; num_docs];
+        let mut counts = vec![0; num_docs + 1];
+        let mut prev = Vec::with_capacity(n);
+        for (i, d) in docs.iter().enumerate() {
+            let d = d.to_usize().unwrap();
+            prev.push(idx(last[d]));
+            last[d] = i + 1;
+            counts[d + 1] += 1;
+        }
+
+        let mut starts = counts;
+        for d in 1..starts.len() {
+            starts[d] += starts[d - 1];
+        }
+        let mut fill = starts.clone();
+        let mut rows: Vec<Index> = (0..n).map(|_| idx(0)).collect();
+        for (i, d) in docs.iter().enumerate() {
+            let d = d.to_usize().unwrap();
+            rows[fill[d]] = idx(i);
+            fill[d] += 1;
+        }
+
+        let min = |a: usize, b: usize| {
+            let at = |row: usize| prev[row].to_usize().unwrap();
+            if at(b) < at(a) {
+                b
+            } else {
+                a
+            }
+        };
+        let blocks = (n + BLOCK - 1) / BLOCK;
+        let mut rmq: Vec<Vec<Index>> = vec![(0..blocks)
+            .map(|b| idx((b * BLOCK..n.min((b + 1) * BLOCK)).reduce(min).unwrap()))
+            .collect()];
+        let mut width = 1;
+        while 2 * width <= blocks {
+            let level = rmq.last().unwrap();
+            let next = (0..=blocks - 2 * width)
+                .map(|b| {
+                    let (a, b) = (&level[b], &level[b + width]);
+                    idx(min(a.to_usize().unwrap(), b.to_usize().unwrap()))
+                })
+                .collect();
+            rmq.push(next);
+            width *= 2;
+        }
+
+        Self {
+            docs,
+            prev,
+            rmq,
+            rows,
+            starts,
+        }
+    }
+
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Document of row `i`
+    #[inline(always)]
+    pub fn doc(&self, i: usize) -> usize {
+        self.docs[i].to_usize().unwrap()
+    }
+
+    /// Row of the minimum of `prev[range]`, which must not be empty.
+    /// Scans at most two partial blocks, the whole ones in between are
+    /// covered by two overlapping entries of the sparse table.
+    fn min_row(&self, range: Range<usize>) -> usize {
+        let min = |a: usize, b: usize| {
+            if self.prev[b].to_usize().unwrap() < self.prev[a].to_usize().unwrap() {
+                b
+            } else {
+                a
+            }
+        };
+        let (first, last) = (range.start / BLOCK, (range.end - 1) / BLOCK);
+        if first == last {
+            return range.reduce(min).unwrap();
+        }
+
+        let mut best = (range.start..(first + 1) * BLOCK).reduce(min).unwrap();
+        best = min(best, (last * BLOCK..range.end).reduce(min).unwrap());
+        let blocks = first + 1..last;
+        if !blocks.is_empty() {
+            let k = (usize::BITS - 1 - blocks.len().leading_zeros()) as usize;
+            best = min(best, self.rmq[k][blocks.start].to_usize().unwrap());
+            best = min(best, self.rmq[k][blocks.end - (1 << k)].to_usize().unwrap());
+        }
+        best
+    }
+
+    /// Returns the distinct documents of the rows in `range`, sorted
+    pub fn documents(&self, range: Range<usize>) -> Vec<usize> {
+        let l = range.start;
+        let mut found = Vec::new();
+        let mut stack = vec![range];
+        while let Some(r) = stack.pop() {
+            if r.is_empty() {
+                continue;
+            }
+            let i = self.min_row(r.clone());
+            if self.prev[i].to_usize().unwrap() > l {
+                // every document here also occurs earlier in the range
+                continue;
+            }
+            found.push(self.doc(i));
+            stack.push(r.start..i);
+            stack.push(i + 1..r.end);
+        }
+        found.sort_unstable();
+        found
+    }
+
+    /// Number of rows of document `doc` in `range`
+    pub fn count(&self, doc: usize, range: Range<usize>) -> usize {
+        let rows = &self.rows[self.starts[doc]..self.starts[doc + 1]];
+        let lo = rows.partition_point(|x| x.to_usize().unwrap() < range.start);
+        let hi = rows.partition_point(|x| x.to_usize().unwrap() < range.end);
+        hi - lo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        naive::{Naive, Xorshift},
+        GeneralizedSuffixArray,
+    };
+
+    #[test]
+    fn across_blocks() {
+        let mut random = Xorshift(0x2545_f491);
+        for &(n, num_docs) in &[(1, 1), (BLOCK, 3), (BLOCK + 1, 2), (1000, 7), (5000, 40)] {
+            let docs: Vec<u32> = (0..n).map(|_| random.next_u32() % num_docs).collect();
+            let table = DocumentTable::new(docs.clone(), num_docs as usize);
+            for _ in 0..200 {
+                let a = random.next_u32() as usize % (n + 1);
+                let b = random.next_u32() as usize % (n + 1);
+                let range = a.min(b)..a.max(b);
+
+                let mut expected: Vec<usize> =
+                    docs[range.clone()].iter().map(|&d| d as usize).collect();
+                expected.sort_unstable();
+                expected.dedup();
+                assert_eq!(table.documents(range.clone()), expected, "{:?}", range);
+                for &d in &expected {
+                    let count = docs[range.clone()].iter().filter(|&&x| x as usize == d);
+                    assert_eq!(table.count(d, range.clone()), count.count());
+                }
+            }
+        }
+
+        // a single row of document 1, anywhere: it is only found if the
+        // minimum is right for every mix of partial and whole blocks
+        let n = 12 * BLOCK;
+        for p in 0..n {
+            let mut docs = vec![0_u32; n];
+            docs[p] = 1;
+            let table = DocumentTable::new(docs, 2);
+            for range in &[0..n, 3..n - 5, BLOCK + 1..8 * BLOCK + 1, 5..BLOCK - 1] {
+                let expected = if range.contains(&p) {
+                    vec![0, 1]
+                } else {
+                    vec![0]
+                };
+                assert_eq!(
+                    table.documents(range.clone()),
+                    expected,
+                    "{} {:?}",
+                    p,
+                    range
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn document_listing() {
+        let docs: &[&[u8]] = &[
+            b"banana",
+            b"",
+            b"ananas\0an",
+            b"an",
+            b"\0ban",
+            b"nab",
+            b"bandana",
+        ];
+        let gsa = GeneralizedSuffixArray::new(&Naive, docs);
+        let table = gsa.document_table();
+        assert_eq!(table.len(), gsa.sa().len());
+
+        let naive = |needle: &[u8]| -> Vec<(usize, usize)> {
+            let mut counts = Vec::new();
+            for (id, doc) in docs.iter().enumerate() {
+                let count = (0..doc.len())
+                    .filter(|&offset| doc[offset..].starts_with(needle))
+                    .count();
+                if count > 0 {
+                    counts.push((id, count));
+                }
+            }
+            counts
+        };
+        let needles: &[&[u8]] = &[
+            b"", b"a", b"an", b"ana", b"ban", b"n", b"na", b"\0", b"\0an", b"s\0a", b"an\0", b"x",
+            b"bandana",
+        ];
+        for needle in needles {
+            let counts = naive(needle);
+            assert_eq!(gsa.document_counts(&table, needle), counts, "{:?}", needle);
+            let listed: Vec<usize> = counts.iter().map(|&(doc, _)| doc).collect();
+            assert_eq!(gsa.documents(&table, needle), listed, "{:?}", needle);
+        }
+    }
+}
@@ -0,0 +1,455 @@
+//! On-disk format for suffix arrays.
+//!
+//! A file is a 32-byte header followed by the suffix array, little-endian:
+//!
+//! | offset | size | field                                 |
+//! |--------|------|---------------------------------------|
+//! | 0      | 8    | magic, `b"sacabase"`                  |
+//! | 8      | 4    | format version, currently 1           |
+//! | 12     | 4    | index width in bytes, 4 or 8          |
+//! | 16     | 8    | text length                           |
+//! | 24     | 8    | FNV-1a 64 checksum of the text        |
+//!
+//! The text itself is not stored: it has to be passed again when loading,
+//! and is checked against the length and checksum from the header.
+
+use crate::SuffixArray;
+use num_traits::ToPrimitive;
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+pub const MAGIC: &[u8; 8] = b"sacabase";
+pub const VERSION: u32 = 1;
+pub const HEADER_LEN: usize = 32;
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Index types that can be stored in a file: `i32`, `u32`, `i64` and `u64`.
+/// Sealed, since `Mapped` reinterprets the file's bytes as a slice of them.
+pub trait FileIndex: private::Sealed + ToPrimitive + Copy {
+    /// Width in bytes
+    const WIDTH: usize;
+    /// Largest value, so loading as a narrower type can be refused
+    const MAX: u64;
+
+    fn write_le(self, buf: &mut [u8]);
+    fn read_le(buf: &[u8]) -> Self;
+}
+
+macro_rules! impl_file_index {
+    ($($t: ty),*) => {
+        $(
+            impl private::Sealed for $t {}
+
+            impl FileIndex for $t {
+                const WIDTH: usize = std::mem::size_of::<$t>();
+                const MAX: u64 = <$t>::MAX as u64;
+
+                #[inline(always)]
+                fn write_le(self, buf: &mut [u8]) {
+                    buf.copy_from_slice(&self.to_le_bytes());
+                }
+
+                #[inline(always)]
+                fn read_le(buf: &[u8]) -> Self {
+                    let mut bytes = [0; std::mem::size_of::<$t>()];
+                    bytes.copy_from_slice(buf);
+                    <$t>::from_le_bytes(bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_file_index!(i32, u32, i64, u64);
+
+/// Error returned when loading a suffix array file
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// Not a suffix array file
+    BadMagic,
+    /// Written with another version of the format
+    UnsupportedVersion(u32),
+    /// The file's index width doesn't match the requested index type
+    WidthMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// The text is too large for the requested index type
+    TooLarge,
+    /// The file was built for a text of a different length
+    LengthMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// The file was built for a different text
+    ChecksumMismatch,
+    /// The file is shorter or longer than its header says
+    BadSize,
+    /// Zero-copy loading needs a little-endian host
+    BigEndianHost,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::BadMagic => write!(f, "not a suffix array file"),
+            Error::UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            Error::WidthMismatch { expected, found } => write!(
+                f,
+                "index width mismatch: expected {} bytes, found {}",
+                expected, found
+            ),
+            Error::TooLarge => write!(f, "text too large for the index type"),
+            Error::LengthMismatch { expected, found } => write!(
+                f,
+                "text length mismatch: expected {}, found {}",
+                expected, found
+            ),
+            Error::ChecksumMismatch => write!(f, "text checksum mismatch"),
+            Error::BadSize => write!(f, "file size doesn't match its header"),
+            Error::BigEndianHost => write!(f, "memory-mapping needs a little-endian host"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// FNV-1a, 64-bit
+pub fn checksum(text: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for &b in text {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+fn header<Index: FileIndex>(text: &[u8]) -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[0..8].copy_from_slice(MAGIC);
+    header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+    header[12..16].copy_from_slice(&(Index::WIDTH as u32).to_le_bytes());
+    header[16..24].copy_from_slice(&(text.len() as u64).to_le_bytes());
+    header[24..32].copy_from_slice(&checksum(text).to_le_bytes());
+    header
+}
+
+/// Checks `header` against `text` and the requested index type
+fn check_header<Index: FileIndex>(text: &[u8], header: &[u8]) -> Result<(), Error> {
+    let u32_at = |i: usize| u32::read_le(&header[i..i + 4]);
+    let u64_at = |i: usize| u64::read_le(&header[i..i + 8]);
+
+    if &header[0..8] != MAGIC {
+        return Err(Error::BadMagic);
+    }
+    let version = u32_at(8);
+    if version != VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    let width = u32_at(12) as usize;
+    if width != Index::WIDTH {
+        return Err(Error::WidthMismatch {
+            expected: Index::WIDTH,
+            found: width,
+        });
+    }
+    let len = u64_at(16);
+    if len > Index::MAX {
+        return Err(Error::TooLarge);
+    }
+    if len != text.len() as u64 {
+        return Err(Error::LengthMismatch {
+            expected: text.len(),
+            found: len as usize,
+        });
+    }
+    if u64_at(24) != checksum(text) {
+        return Err(Error::ChecksumMismatch);
+    }
+    Ok(())
+}
+
+/// Writes `sa`, the suffix array of `text`, to `w`
+pub fn write<W: Write, Index: FileIndex>(text: &[u8], sa: &[Index], mut w: W) -> io::Result<()> {
+    assert_eq!(
+        text.len(),
+        sa.len(),
+        "text and suffix array should have same len"
+    );
+
+    w.write_all(&header::<Index>(text))?;
+    let mut buf = vec![0; 4096 * Index::WIDTH];
+    for chunk in sa.chunks(4096) {
+        let buf = &mut buf[..chunk.len() * Index::WIDTH];
+        for (x, out) in chunk.iter().zip(buf.chunks_mut(Index::WIDTH)) {
+            x.write_le(out);
+        }
+        w.write_all(buf)?;
+    }
+    w.flush()
+}
+
+/// Reads the suffix array of `text` from `r`
+pub fn read<'a, R: Read, Index: FileIndex>(
+    text: &'a [u8],
+    mut r: R,
+) -> Result<SuffixArray<'a, Index>, Error> {
+    let mut read_exact = |buf: &mut [u8]| {
+        r.read_exact(buf).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Error::BadSize,
+            _ => Error::Io(e),
+        })
+    };
+
+    let mut header = [0; HEADER_LEN];
+    read_exact(&mut header)?;
+    check_header::<Index>(text, &header)?;
+
+    let mut sa = Vec::with_capacity(text.len());
+    let mut buf = vec![0; 4096 * Index::WIDTH];
+    while sa.len() < text.len() {
+        let count = (text.len() - sa.len()).min(4096);
+        let buf = &mut buf[..count * Index::WIDTH];
+        read_exact(buf)?;
+        sa.extend(buf.chunks(Index::WIDTH).map(Index::read_le));
+    }
+    if r.read(&mut [0])? != 0 {
+        return Err(Error::BadSize);
+    }
+    Ok(SuffixArray::new(text, sa))
+}
+
+/// Saves `sa`, the suffix array of `text`, to the file at `path`
+pub fn save<P: AsRef<Path>, Index: FileIndex>(
+    text: &[u8],
+    sa: &[Index],
+    path: P,
+) -> io::Result<()> {
+    write(text, sa, BufWriter::new(File::create(path)?))
+}
+
+/// Loads the suffix array of `text` from the file at `path`, into memory.
+/// See `Mapped` to use it without copying.
+pub fn load<'a, P: AsRef<Path>, Index: FileIndex>(
+    text: &'a [u8],
+    path: P,
+) -> Result<SuffixArray<'a, Index>, Error> {
+    read(text, BufReader::new(File::open(path)?))
+}
+
+#[cfg(feature = "mmap")]
+pub use mapped::Mapped;
+
+#[cfg(feature = "mmap")]
+mod mapped {
+    use super::{check_header, Error, FileIndex, HEADER_LEN};
+    use crate::{LongestCommonSubstring, NotSorted, Occurrences};
+    use memmap2::Mmap;
+    use std::{fs::File, io, marker::PhantomData, mem, ops::Range, path::Path, slice};
+
+    /// A suffix array file, memory-mapped: searches read the array
+    /// straight from the page cache, without loading it first.
+    ///
+    /// As with any memory map, the file must not be modified while
+    /// it's mapped.
+    pub struct Mapped<'a, Index> {
+        text: &'a [u8],
+        map: Mmap,
+        index: PhantomData<Index>,
+    }
+
+    impl<'a, Index> Mapped<'a, Index>
+    where
+        Index: FileIndex,
+    {
+        /// Maps the suffix array of `text` from the file at `path`.
+        /// Only the header is read, but the text is checksummed.
+        pub fn open<P: AsRef<Path>>(text: &'a [u8], path: P) -> Result<Self, Error> {
+            if cfg!(target_endian = "big") {
+                return Err(Error::BigEndianHost);
+            }
+
+            let file = File::open(path)?;
+            let map = unsafe { Mmap::map(&file)? };
+            if map.len() < HEADER_LEN {
+                return Err(Error::BadSize);
+            }
+            check_header::<Index>(text, &map[..HEADER_LEN])?;
+            if map.len() != HEADER_LEN + text.len() * Index::WIDTH {
+                return Err(Error::BadSize);
+            }
+            // maps are page-aligned, and the header keeps 8-byte alignment,
+            // but `as_slice` relies on it
+            if map[HEADER_LEN..].as_ptr() as usize % mem::align_of::<Index>() != 0 {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "suffix array is not aligned in memory",
+                )));
+            }
+
+            Ok(Self {
+                text,
+                map,
+                index: PhantomData,
+            })
+        }
+
+        /// The suffix array, backed by the file
+        pub fn as_slice(&self) -> &[Index] {
+            let bytes = &self.map[HEADER_LEN..];
+            // alignment and size are checked in `open`, and every bit
+            // pattern is a valid integer
+            unsafe {
+                slice::from_raw_parts(bytes.as_ptr() as *const Index, bytes.len() / Index::WIDTH)
+            }
+        }
+
+        pub fn text(&self) -> &'a [u8] {
+            self.text
+        }
+
+        /// Returns the longest substring of the text that matches
+        /// a prefix of `needle`
+        pub fn longest_substring_match(&self, needle: &[u8]) -> LongestCommonSubstring<'a> {
+            crate::longest_substring_match(self.text, self.as_slice(), needle)
+        }
+
+        /// Returns the interval of the suffix array whose suffixes start with `needle`
+        pub fn range(&self, needle: &[u8]) -> Range<usize> {
+            crate::occurrence_range(self.text, self.as_slice(), needle)
+        }
+
+        /// Returns the number of occurrences of `needle` in the text
+        pub fn count(&self, needle: &[u8]) -> usize {
+            self.range(needle).len()
+        }
+
+        /// Returns the positions of all occurrences of `needle` in the text
+        pub fn find_all(&self, needle: &[u8]) -> Occurrences<'_, Index> {
+            crate::find_all(self.text, self.as_slice(), needle)
+        }
+
+        /// Checks that the file really holds the suffix array of the text,
+        /// which `open` doesn't do, in O(n²) worst case
+        pub fn verify(&self) -> Result<(), NotSorted> {
+            crate::verify(self.text, self.as_slice())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{file, tests::banana, SuffixArray};
+
+    #[test]
+    fn file_round_trip() {
+        let sa = banana();
+        let mut buf = Vec::new();
+        file::write(b"banana", &sa.sa[..], &mut buf).unwrap();
+        assert_eq!(buf.len(), file::HEADER_LEN + 6 * 4);
+        assert_eq!(&buf[..8], file::MAGIC);
+
+        let loaded: SuffixArray<u32> = file::read(b"banana", &buf[..]).unwrap();
+        assert_eq!(loaded.sa, sa.sa);
+        // same width, other signedness
+        let loaded: SuffixArray<i32> = file::read(b"banana", &buf[..]).unwrap();
+        assert_eq!(loaded.sa, vec![5, 3, 1, 0, 4, 2]);
+
+        macro_rules! read_err {
+            ($text: expr, $buf: expr) => {
+                file::read::<_, u32>($text, &$buf[..]).err().unwrap()
+            };
+        }
+        assert!(matches!(
+            read_err!(b"bananb", buf),
+            file::Error::ChecksumMismatch
+        ));
+        assert!(matches!(
+            read_err!(b"banan", buf),
+            file::Error::LengthMismatch { .. }
+        ));
+        assert!(matches!(
+            read_err!(b"banana", buf[..40]),
+            file::Error::BadSize
+        ));
+        assert!(matches!(
+            read_err!(b"banana", [&buf[..], &[0]].concat()),
+            file::Error::BadSize
+        ));
+        assert!(matches!(
+            file::read::<_, u64>(b"banana", &buf[..]).err().unwrap(),
+            file::Error::WidthMismatch {
+                expected: 8,
+                found: 4
+            }
+        ));
+
+        let mut bad = buf.clone();
+        bad[0] = b'S';
+        assert!(matches!(read_err!(b"banana", bad), file::Error::BadMagic));
+        let mut bad = buf.clone();
+        bad[8] = 2;
+        assert!(matches!(
+            read_err!(b"banana", bad),
+            file::Error::UnsupportedVersion(2)
+        ));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn file_mapped() {
+        let text = b"how much wood would a woodchuck chuck";
+        let mut sa: Vec<i64> = (0..text.len() as i64).collect();
+        sa.sort_by_key(|&i| &text[i as usize..]);
+        let sa = SuffixArray::new(&text[..], sa);
+
+        let path = std::env::temp_dir().join(format!("sacabase-{}.sa", std::process::id()));
+        sa.save(&path).unwrap();
+
+        let loaded = SuffixArray::<i64>::load(text, &path).unwrap();
+        assert_eq!(loaded.sa, sa.sa);
+
+        let mapped = file::Mapped::<i64>::open(text, &path).unwrap();
+        assert_eq!(mapped.as_slice(), &sa.sa[..]);
+        mapped.verify().unwrap();
+        for needle in &[&b"wood"[..], b"chuck", b"woodpecker", b"z", b""] {
+            assert_eq!(mapped.range(needle), sa.range(needle));
+            assert!(mapped.find_all(needle).eq(sa.find_all(needle)));
+            let lcs = mapped.longest_substring_match(needle);
+            assert_eq!(lcs.len(), sa.longest_substring_match(needle).len());
+            assert_eq!(lcs.as_bytes(), &needle[..lcs.len()]);
+        }
+        assert!(matches!(
+            file::Mapped::<i64>::open(&text[1..], &path).err().unwrap(),
+            file::Error::LengthMismatch { .. }
+        ));
+        assert!(matches!(
+            file::Mapped::<u32>::open(text, &path).err().unwrap(),
+            file::Error::WidthMismatch { .. }
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
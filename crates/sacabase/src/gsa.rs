@@ -0,0 +1,404 @@
+//! Generalized suffix array: one suffix array over many documents.
+//!
+//! Documents are concatenated, each followed by a separator that must be
+//! unique for matches to stop there, as in `joint`: if some byte value
+//! occurs in no document, it's used as is. Otherwise every byte is encoded
+//! as two, with `[0, 0]` as the separator, only suffixes at even positions
+//! are kept, and needles are encoded the same way before searching.
+
+use crate::{
+    joint::{escape, unused_byte},
+    occurrence_range, DocumentTable, NotSorted, Occurrences, Saca,
+};
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::{borrow::Cow, ops::Range};
+
+/// A match inside a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DocMatch {
+    pub doc: usize,
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// A suffix array over several documents
+pub struct GeneralizedSuffixArray<Index> {
+    /// Documents and separators, encoded if `width` is 2
+    text: Vec<u8>,
+    /// Positions in `text`, all multiples of `width`
+    sa: Vec<Index>,
+    /// Bytes of `text` per byte of the documents
+    width: usize,
+    /// Separator, if `width` is 1
+    separator: Option<u8>,
+    /// Start of every document, followed by the end of the last separator,
+    /// in document bytes
+    starts: Vec<usize>,
+}
+
+impl<Index> GeneralizedSuffixArray<Index>
+where
+    Index: ToPrimitive,
+{
+    /// Concatenates `docs` and sorts them with `saca`
+    pub fn new<S, I, D>(saca: &S, docs: I) -> Self
+    where
+        S: Saca<Index = Index>,
+        I: IntoIterator<Item = D>,
+        D: AsRef<[u8]>,
+    {
+        let docs: Vec<D> = docs.into_iter().collect();
+        let all: Vec<&[u8]> = docs.iter().map(|d| d.as_ref()).collect();
+        let separator = unused_byte(&all);
+
+        let mut text = Vec::new();
+        let mut starts = Vec::new();
+        let mut start = 0;
+        for doc in all {
+            starts.push(start);
+            start += doc.len() + 1;
+            match separator {
+                Some(sep) => {
+                    text.extend_from_slice(doc);
+                    text.push(sep);
+                }
+                None => {
+                    text.extend(escape(doc));
+                    text.extend_from_slice(&[0, 0]);
+                }
+            }
+        }
+        starts.push(start);
+        assert!(
+            text.len() <= saca.max_len(),
+            "documents too large for {}",
+            saca.name()
+        );
+
+        let width = if separator.is_some() { 1 } else { 2 };
+        let (_, mut sa) = saca.sort(&text).into_parts();
+        sa.retain(|x| x.to_usize().unwrap() % width == 0);
+        Self {
+            text,
+            sa,
+            width,
+            separator,
+            starts,
+        }
+    }
+
+    pub fn num_docs(&self) -> usize {
+        self.starts.len() - 1
+    }
+
+    /// Returns document `id`, without its separator. It's decoded if
+    /// `text()` is encoded.
+    pub fn doc(&self, id: usize) -> Cow<'_, [u8]> {
+        let (start, end) = (self.starts[id], self.starts[id + 1] - 1);
+        match self.separator {
+            Some(_) => Cow::Borrowed(&self.text[start..end]),
+            None => Cow::Owned(
+                self.text[2 * start..2 * end]
+                    .chunks(2)
+                    .map(|x| (x[0] - 1) * 16 + x[1])
+                    .collect(),
+            ),
+        }
+    }
+
+    fn doc_len(&self, id: usize) -> usize {
+        self.starts[id + 1] - 1 - self.starts[id]
+    }
+
+    /// Byte separating documents, or `None` if every byte value occurs
+    /// in them, in which case `text()` is encoded
+    pub fn separator(&self) -> Option<u8> {
+        self.separator
+    }
+
+    /// The indexed text: documents, each followed by the separator, with
+    /// every byte `x` encoded as `[1 + x / 16, x % 16]` and `[0, 0]` as
+    /// the separator if `separator()` is `None`
+    pub fn text(&self) -> &[u8] {
+        &self.text[..]
+    }
+
+    /// Suffix array of `text()`, which only has even positions if it's
+    /// encoded
+    pub fn sa(&self) -> &[Index] {
+        &self.sa[..]
+    }
+
+    /// Returns the `(doc_id, offset)` of a position in the documents,
+    /// counting one byte for every separator. A separator is at offset
+    /// `doc(doc_id).len()`.
+    pub fn locate(&self, pos: usize) -> (usize, usize) {
+        assert!(pos < *self.starts.last().unwrap(), "position out of bounds");
+        let doc = self.starts.partition_point(|&s| s <= pos) - 1;
+        (doc, pos - self.starts[doc])
+    }
+
+    /// Returns the `(doc_id, offset)` of the occurrence of a `len`-byte
+    /// string at `pos` of `text()`, if it doesn't start on a separator
+    fn contained(&self, pos: usize, len: usize) -> Option<(usize, usize)> {
+        let (doc, offset) = self.locate(pos / self.width);
+        let doc_len = self.doc_len(doc);
+        if offset < doc_len && offset + len <= doc_len {
+            Some((doc, offset))
+        } else {
+            None
+        }
+    }
+
+    /// Encodes `needle` like the documents
+    fn encode<'b>(&self, needle: &'b [u8]) -> Cow<'b, [u8]> {
+        match self.separator {
+            Some(_) => Cow::Borrowed(needle),
+            None => Cow::Owned(escape(needle)),
+        }
+    }
+
+    /// Rows of the suffixes starting with `needle`
+    fn range(&self, needle: &[u8]) -> Range<usize> {
+        match self.separator {
+            // only occurs across documents
+            Some(sep) if needle.contains(&sep) => 0..0,
+            _ => occurrence_range(&self.text, &self.sa[..], &self.encode(needle)),
+        }
+    }
+
+    /// Returns the number of occurrences of `needle` in all documents
+    pub fn count(&self, needle: &[u8]) -> usize {
+        if needle.is_empty() {
+            // every position but the separators
+            return *self.starts.last().unwrap() - self.num_docs();
+        }
+        self.range(needle).len()
+    }
+
+    /// Returns the `(doc_id, offset)` of all occurrences of `needle`,
+    /// in suffix array order
+    pub fn find_all(&self, needle: &[u8]) -> DocOccurrences<'_, Index> {
+        DocOccurrences {
+            gsa: self,
+            inner: Occurrences {
+                inner: self.sa[self.range(needle)].iter(),
+            },
+            len: needle.len(),
+        }
+    }
+
+    /// Returns the longest prefix of `needle` that occurs in a document.
+    pub fn longest_substring_match(&self, needle: &[u8]) -> DocMatch {
+        let none = DocMatch {
+            doc: 0,
+            offset: 0,
+            len: 0,
+        };
+        if self.sa.is_empty() {
+            // no documents
+            return none;
+        }
+
+        // a match can't go past the separator
+        let needle = match self.separator {
+            Some(sep) => match needle.iter().position(|&x| x == sep) {
+                Some(end) => &needle[..end],
+                None => needle,
+            },
+            None => needle,
+        };
+        let lcs = crate::longest_substring_match(&self.text, &self.sa[..], &self.encode(needle));
+        // an encoded match may end halfway through a byte
+        let len = lcs.len() / self.width;
+        if len == 0 {
+            return none;
+        }
+        let (doc, offset) = self.locate(lcs.start() / self.width);
+        DocMatch { doc, offset, len }
+    }
+
+    /// Builds the side tables needed by `documents` and `document_counts`
+    pub fn document_table(&self) -> DocumentTable<Index>
+    where
+        Index: FromPrimitive,
+    {
+        let docs = self
+            .sa
+            .iter()
+            .map(|x| {
+                let (doc, _) = self.locate(x.to_usize().unwrap() / self.width);
+                Index::from_usize(doc).unwrap()
+            })
+            .collect();
+        DocumentTable::new(docs, self.num_docs())
+    }
+
+    /// Returns the distinct documents containing `needle`, sorted,
+    /// using a table built by `document_table`
+    pub fn documents(&self, table: &DocumentTable<Index>, needle: &[u8]) -> Vec<usize>
+    where
+        Index: FromPrimitive,
+    {
+        if needle.is_empty() {
+            return (0..self.num_docs())
+                .filter(|&doc| self.doc_len(doc) > 0)
+                .collect();
+        }
+        table.documents(self.range(needle))
+    }
+
+    /// Returns `(doc_id, count)` for every document containing `needle`,
+    /// sorted by document, using a table built by `document_table`
+    pub fn document_counts(
+        &self,
+        table: &DocumentTable<Index>,
+        needle: &[u8],
+    ) -> Vec<(usize, usize)>
+    where
+        Index: FromPrimitive,
+    {
+        if needle.is_empty() {
+            return (0..self.num_docs())
+                .map(|doc| (doc, self.doc_len(doc)))
+                .filter(|&(_, count)| count > 0)
+                .collect();
+        }
+        let range = self.range(needle);
+        table
+            .documents(range.clone())
+            .into_iter()
+            .map(|doc| (doc, table.count(doc, range.clone())))
+            .collect()
+    }
+
+    pub fn verify(&self) -> Result<(), NotSorted> {
+        let suff = |i: usize| &self.text[self.sa[i].to_usize().unwrap()..];
+        for i in 1..self.sa.len() {
+            if suff(i - 1) >= suff(i) {
+                return Err(NotSorted { i: i - 1, j: i });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the `(doc_id, offset)` of every occurrence of a needle,
+/// returned by `GeneralizedSuffixArray::find_all`
+pub struct DocOccurrences<'a, Index>
+where
+    Index: ToPrimitive,
+{
+    gsa: &'a GeneralizedSuffixArray<Index>,
+    inner: Occurrences<'a, Index>,
+    len: usize,
+}
+
+impl<'a, Index> Iterator for DocOccurrences<'a, Index>
+where
+    Index: ToPrimitive,
+{
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        let (gsa, len) = (self.gsa, self.len);
+        // skips separators, for the empty needle
+        self.inner.find_map(|pos| gsa.contained(pos, len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::naive::Naive;
+
+    #[test]
+    fn generalized() {
+        let every_byte: Vec<u8> = (0..=255).collect();
+        let sets: Vec<(Vec<&[u8]>, Option<u8>)> = vec![
+            (
+                vec![b"banana", b"", b"ananas\0an", b"an", b"\0ban"],
+                Some(1),
+            ),
+            // no byte left for a separator
+            (
+                vec![
+                    b"banana",
+                    &every_byte[..],
+                    b"ananas\0an",
+                    b"",
+                    b"\x01\xffban",
+                ],
+                None,
+            ),
+        ];
+        for (docs, separator) in &sets {
+            let gsa = GeneralizedSuffixArray::new(&Naive, docs);
+            gsa.verify().unwrap();
+            assert_eq!(gsa.separator(), *separator);
+            assert_eq!(gsa.num_docs(), 5);
+            for (id, doc) in docs.iter().enumerate() {
+                assert_eq!(gsa.doc(id), *doc);
+            }
+            assert_eq!(gsa.locate(7), (1, 0));
+            assert_eq!(gsa.locate(6), (0, 6));
+
+            let naive = |needle: &[u8]| {
+                let mut found = Vec::new();
+                for (id, doc) in docs.iter().enumerate() {
+                    for offset in 0..doc.len() {
+                        if doc[offset..].starts_with(needle) {
+                            found.push((id, offset));
+                        }
+                    }
+                }
+                found
+            };
+            let needles: &[&[u8]] = &[
+                b"",
+                b"a",
+                b"an",
+                b"ana",
+                b"nan",
+                b"\0",
+                b"\0an",
+                b"a\0a",
+                b"s\0a",
+                b"an\0",
+                b"\0b",
+                b"na\0",
+                b"x",
+                b"\x01",
+                b"\xff\0",
+                b"\xffb",
+                b"banana\0",
+                b"banana\x01",
+                b"banana\x01an",
+                b"\xfe\xff\0\x01",
+            ];
+            for needle in needles {
+                let mut found: Vec<_> = gsa.find_all(needle).collect();
+                found.sort();
+                assert_eq!(found, naive(needle), "{:?}", needle);
+                assert_eq!(gsa.count(needle), found.len(), "{:?}", needle);
+
+                // longest prefix of the needle occurring in a document
+                let m = gsa.longest_substring_match(needle);
+                let len = (0..=needle.len())
+                    .rev()
+                    .find(|&len| len == 0 || !naive(&needle[..len]).is_empty())
+                    .unwrap();
+                assert_eq!(m.len, len, "{:?}", needle);
+                assert_eq!(&gsa.doc(m.doc)[m.offset..m.offset + m.len], &needle[..len]);
+            }
+        }
+
+        let empty = GeneralizedSuffixArray::new(&Naive, Vec::<&[u8]>::new());
+        assert_eq!(empty.num_docs(), 0);
+        for needle in &[&b""[..], b"a", b"\0a"] {
+            assert_eq!(empty.count(needle), 0);
+            let m = empty.longest_substring_match(needle);
+            assert_eq!((m.doc, m.offset, m.len), (0, 0, 0));
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::fmt;
+
+/// Inverse suffix array, a.k.a. rank array: `isa[sa[i]] == i`,
+/// so `isa[i]` is the lexicographic rank of the suffix starting at `i`.
+pub struct InverseSuffixArray<Index> {
+    isa: Vec<Index>,
+}
+
+impl<Index> fmt::Debug for InverseSuffixArray<Index>
+where
+    Index: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ISA{:?}", self.isa)
+    }
+}
+
+impl<Index> InverseSuffixArray<Index>
+where
+    Index: ToPrimitive,
+{
+    /// Create an instance of InverseSuffixArray, taking ownership of `isa`
+    pub fn new(isa: Vec<Index>) -> Self {
+        Self { isa }
+    }
+
+    /// Returns the rank of the suffix starting at `i`
+    #[inline(always)]
+    pub fn rank(&self, i: usize) -> usize {
+        self.isa[i].to_usize().unwrap()
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.isa.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.isa.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn as_slice(&self) -> &[Index] {
+        &self.isa[..]
+    }
+
+    /// Return the underlying vector, giving back ownership
+    pub fn into_vec(self) -> Vec<Index> {
+        self.isa
+    }
+}
+
+/// Computes the inverse of suffix array `sa`, in O(n).
+pub fn inverse<Index>(sa: &[Index]) -> InverseSuffixArray<Index>
+where
+    Index: ToPrimitive + FromPrimitive,
+{
+    let mut isa: Vec<Index> = (0..sa.len())
+        .map(|_| Index::from_usize(0).unwrap())
+        .collect();
+    for (i, x) in sa.iter().enumerate() {
+        isa[x.to_usize().unwrap()] = Index::from_usize(i).unwrap();
+    }
+    InverseSuffixArray::new(isa)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::banana;
+
+    #[test]
+    fn inverse() {
+        let sa = banana();
+        let isa = sa.inverse();
+        assert_eq!(isa.as_slice(), &[3, 2, 5, 1, 4, 0]);
+        let (_, sa) = sa.into_parts();
+        for (i, &x) in sa.iter().enumerate() {
+            assert_eq!(isa.rank(x as usize), i);
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Matches between two texts, from the suffix array of both.
+//!
+//! The texts are joined as `a`, a separator, then `b`. The separator must
+//! be unique for matches to stop there: if some byte value occurs in
+//! neither text, it's used as is. Otherwise every byte is encoded as two,
+//! `x` as `[1 + x / 16, x % 16]`, with `[0, 0]` as the separator, and only
+//! suffixes at even positions are kept: the encoding preserves their order.
+
+use crate::{LcpArray, Saca};
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::{collections::BTreeMap, mem};
+
+/// Returns a byte value that occurs in none of `texts`, if there's one
+pub(crate) fn unused_byte(texts: &[&[u8]]) -> Option<u8> {
+    let mut used = [false; 256];
+    for &x in texts.iter().flat_map(|t| t.iter()) {
+        used[x as usize] = true;
+    }
+    used.iter().position(|&used| !used).map(|x| x as u8)
+}
+
+/// Encodes every byte as two, `x` as `[1 + x / 16, x % 16]`, so that
+/// `[0, 0]` is free to use as a separator
+pub(crate) fn escape(text: &[u8]) -> Vec<u8> {
+    text.iter().flat_map(|&x| [1 + x / 16, x % 16]).collect()
+}
+
+/// A match between `a[a..a + len]` and `b[b..b + len]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Mem {
+    pub a: usize,
+    pub b: usize,
+    pub len: usize,
+}
+
+/// Byte before a suffix, or 256 if there's none (the start of `a`, or the
+/// separator before `b`): those differ from everything, including 256
+const NO_BYTE: u16 = 256;
+
+/// The suffix array of two texts, with its LCP array
+pub struct JointSuffixArray<'a, Index> {
+    a: &'a [u8],
+    b: &'a [u8],
+    /// Positions in the joint text, `a.len()` being the separator
+    sa: Vec<Index>,
+    lcp: LcpArray<Index>,
+}
+
+impl<'a, Index> JointSuffixArray<'a, Index>
+where
+    Index: ToPrimitive + FromPrimitive,
+{
+    /// Sorts the suffixes of `a` and `b` with `saca`
+    pub fn new<S>(saca: &S, a: &'a [u8], b: &'a [u8]) -> Self
+    where
+        S: Saca<Index = Index>,
+    {
+        let (text, width) = match unused_byte(&[a, b]) {
+            Some(sep) => ([a, &[sep], b].concat(), 1),
+            None => ([escape(a), vec![0, 0], escape(b)].concat(), 2),
+        };
+        assert!(
+            text.len() <= saca.max_len(),
+            "texts too large for {}",
+            saca.name()
+        );
+
+        let (_, sa) = saca.sort(&text).into_parts();
+        let sa: Vec<Index> = sa
+            .into_iter()
+            .filter_map(|x| {
+                let pos = x.to_usize().unwrap();
+                if pos % width == 0 {
+                    Index::from_usize(pos / width)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        drop(text);
+
+        let mut joint = Self {
+            a,
+            b,
+            sa,
+            lcp: LcpArray::new(Vec::new()),
+        };
+        joint.lcp = joint.kasai();
+        joint
+    }
+
+    /// Length of the joint text
+    fn len(&self) -> usize {
+        self.a.len() + 1 + self.b.len()
+    }
+
+    /// Suffix array of the joint text, whose separator is at `a.len()`
+    pub fn sa(&self) -> &[Index] {
+        &self.sa[..]
+    }
+
+    pub fn lcp(&self) -> &LcpArray<Index> {
+        &self.lcp
+    }
+
+    /// Byte at position `i` of the joint text, `None` for the separator
+    #[inline(always)]
+    fn at(&self, i: usize) -> Option<u8> {
+        let n = self.a.len();
+        if i < n {
+            Some(self.a[i])
+        } else if i == n {
+            None
+        } else {
+            Some(self.b[i - n - 1])
+        }
+    }
+
+    /// Kasai's algorithm, on the joint text
+    fn kasai(&self) -> LcpArray<Index> {
+        let n = self.len();
+        let mut rank = vec![0; n];
+        for (i, x) in self.sa.iter().enumerate() {
+            rank[x.to_usize().unwrap()] = i;
+        }
+
+        let mut lcp: Vec<Index> = (0..n).map(|_| Index::from_usize(0).unwrap()).collect();
+        let mut h = 0;
+        for i in 0..n {
+            if rank[i] == 0 {
+                h = 0;
+                continue;
+            }
+            let j = self.sa[rank[i] - 1].to_usize().unwrap();
+            while i + h < n && j + h < n {
+                match (self.at(i + h), self.at(j + h)) {
+                    (Some(x), Some(y)) if x == y => h += 1,
+                    _ => break,
+                }
+            }
+            lcp[rank[i]] = Index::from_usize(h).unwrap();
+            h = h.saturating_sub(1);
+        }
+        LcpArray::new(lcp)
+    }
+
+    #[inline(always)]
+    fn left(&self, pos: usize) -> u16 {
+        if pos == 0 || pos == self.a.len() + 1 {
+            NO_BYTE
+        } else {
+            self.at(pos - 1).unwrap() as u16
+        }
+    }
+
+    /// Returns the maximal exact matches of at least `min_len` bytes:
+    /// matches that can't be extended to the left or to the right.
+    /// Sorted by position in `a`, then in `b`.
+    pub fn mems(&self, min_len: usize) -> Vec<Mem> {
+        self.traverse(min_len, true)
+    }
+
+    /// Returns the maximal unique matches of at least `min_len` bytes:
+    /// maximal matches occurring exactly once in `a` and once in `b`.
+    /// Sorted by position in `a`.
+    pub fn mums(&self, min_len: usize) -> Vec<Mem> {
+        self.traverse(min_len, false)
+    }
+
+    /// Bottom-up traversal of the LCP intervals of at least `min_len`.
+    ///
+    /// Every interval keeps the positions of its suffixes, grouped by
+    /// text and by the byte before them. When a child interval (or a
+    /// single suffix) is added to an interval of value `ℓ`, suffixes of
+    /// the child and of the previous children share exactly `ℓ` bytes,
+    /// so pairs from `a` and `b` preceded by different bytes are MEMs.
+    fn traverse(&self, min_len: usize, mems: bool) -> Vec<Mem> {
+        let min_len = min_len.max(1);
+        let n = self.sa.len();
+        let sep = self.a.len();
+        let mut found = Vec::new();
+
+        let mut stack = vec![Node::new(0)];
+        for i in 0..n {
+            let pos = self.sa[i].to_usize().unwrap();
+            let mut node = Node::new(usize::MAX);
+            node.size = 1;
+            if pos != sep {
+                let (a, b) = node.groups.entry(self.left(pos)).or_default();
+                if pos < sep {
+                    a.push(pos);
+                } else {
+                    b.push(pos - sep - 1);
+                }
+            }
+
+            let cur = if i + 1 < n { self.lcp.get(i + 1) } else { 0 };
+            while cur < stack.last().unwrap().lcp {
+                let mut top = stack.pop().unwrap();
+                top.add(node, min_len, mems.then_some(&mut found));
+                if !mems && top.lcp >= min_len {
+                    found.extend(top.mum());
+                }
+                node = top;
+            }
+            if cur > stack.last().unwrap().lcp {
+                let mut top = Node::new(cur);
+                top.add(node, min_len, None);
+                stack.push(top);
+            } else {
+                let top = stack.last_mut().unwrap();
+                top.add(node, min_len, mems.then_some(&mut found));
+            }
+        }
+
+        found.sort_unstable();
+        found
+    }
+}
+
+/// An LCP interval, or a single suffix, during the traversal
+struct Node {
+    lcp: usize,
+    /// Number of suffixes
+    size: usize,
+    /// Positions in `a` and in `b`, by preceding byte
+    groups: BTreeMap<u16, (Vec<usize>, Vec<usize>)>,
+}
+
+impl Node {
+    fn new(lcp: usize) -> Self {
+        Self {
+            lcp,
+            size: 0,
+            groups: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a child, reporting the MEMs between it and the previous
+    /// children to `found`
+    fn add(&mut self, child: Node, min_len: usize, found: Option<&mut Vec<Mem>>) {
+        self.size += child.size;
+        if self.lcp < min_len {
+            // and neither will its ancestors
+            return;
+        }
+
+        if let Some(found) = found {
+            let len = self.lcp;
+            for (&x, (a1, b1)) in &child.groups {
+                for (&y, (a2, b2)) in &self.groups {
+                    if x == y && x != NO_BYTE {
+                        continue;
+                    }
+                    for (aa, bb) in [(a1, b2), (a2, b1)] {
+                        for &a in aa {
+                            found.extend(bb.iter().map(|&b| Mem { a, b, len }));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (x, (mut a, mut b)) in child.groups {
+            let (a2, b2) = self.groups.entry(x).or_default();
+            for (from, to) in [(&mut a, a2), (&mut b, b2)] {
+                if from.len() > to.len() {
+                    mem::swap(from, to);
+                }
+                to.append(from);
+            }
+        }
+    }
+
+    /// The MUM of this interval, if it's one
+    fn mum(&self) -> Option<Mem> {
+        if self.size != 2 {
+            return None;
+        }
+        let mut a = None;
+        let mut b = None;
+        let mut lefts = Vec::new();
+        for (&x, (aa, bb)) in &self.groups {
+            for &pos in aa {
+                a = Some(pos);
+                lefts.push(x);
+            }
+            for &pos in bb {
+                b = Some(pos);
+                lefts.push(x);
+            }
+        }
+        if lefts.len() == 2 && lefts[0] == lefts[1] && lefts[0] != NO_BYTE {
+            return None;
+        }
+        Some(Mem {
+            a: a?,
+            b: b?,
+            len: self.lcp,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common_prefix_len,
+        naive::{Naive, Xorshift},
+    };
+
+    #[test]
+    fn mems_and_mums() {
+        fn naive(a: &[u8], b: &[u8], min_len: usize) -> (Vec<Mem>, Vec<Mem>) {
+            let count =
+                |t: &[u8], s: &[u8]| (0..t.len()).filter(|&i| t[i..].starts_with(s)).count();
+            let mut mems = Vec::new();
+            for i in 0..a.len() {
+                for j in 0..b.len() {
+                    let len = common_prefix_len(&a[i..], &b[j..]);
+                    if len >= min_len.max(1) && (i == 0 || j == 0 || a[i - 1] != b[j - 1]) {
+                        mems.push(Mem { a: i, b: j, len });
+                    }
+                }
+            }
+            let mums = mems
+                .iter()
+                .filter(|m| {
+                    let s = &a[m.a..m.a + m.len];
+                    count(a, s) == 1 && count(b, s) == 1
+                })
+                .cloned()
+                .collect();
+            (mems, mums)
+        }
+
+        let mut random = Xorshift(0x2545_f491);
+        let every_byte: Vec<u8> = (0..=255).collect();
+        let pairs: Vec<(Vec<u8>, Vec<u8>)> = vec![
+            (b"".to_vec(), b"abc".to_vec()),
+            (b"banana".to_vec(), b"ananas".to_vec()),
+            (b"abracadabra".to_vec(), b"cadabra abra".to_vec()),
+            (random.text(200, b"ab"), random.text(150, b"ab")),
+            (random.text(300, b"acgt"), random.text(300, b"acgt")),
+            // no byte left for a separator
+            (
+                [&every_byte[..], &random.text(100, b"\0\x01\xff")].concat(),
+                random.text(200, b"\0\x01\xff"),
+            ),
+        ];
+        for (a, b) in &pairs {
+            let joint = JointSuffixArray::new(&Naive, a, b);
+            for min_len in [0, 1, 3, 8] {
+                let (mems, mums) = naive(a, b, min_len);
+                assert_eq!(joint.mems(min_len), mems, "{:?} {:?} {}", a, b, min_len);
+                assert_eq!(joint.mums(min_len), mums, "{:?} {:?} {}", a, b, min_len);
+            }
+        }
+
+        let joint = JointSuffixArray::new(&Naive, b"xabcdy", b"zabcdw");
+        assert_eq!(joint.mums(0), vec![Mem { a: 1, b: 1, len: 4 }]);
+    }
+}
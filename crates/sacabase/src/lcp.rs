@@ -0,0 +1,168 @@
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::fmt;
+
+/// Longest common prefix array: `lcp[i]` is the length of the longest
+/// common prefix of the suffixes at `sa[i - 1]` and `sa[i]`.
+/// `lcp[0]` is always 0.
+pub struct LcpArray<Index> {
+    lcp: Vec<Index>,
+}
+
+impl<Index> fmt::Debug for LcpArray<Index>
+where
+    Index: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LCP{:?}", self.lcp)
+    }
+}
+
+impl<Index> LcpArray<Index>
+where
+    Index: ToPrimitive,
+{
+    /// Create an instance of LcpArray, taking ownership of `lcp`
+    pub fn new(lcp: Vec<Index>) -> Self {
+        Self { lcp }
+    }
+
+    /// Returns the LCP of the suffixes at `sa[i - 1]` and `sa[i]`
+    #[inline(always)]
+    pub fn get(&self, i: usize) -> usize {
+        self.lcp[i].to_usize().unwrap()
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.lcp.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.lcp.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn as_slice(&self) -> &[Index] {
+        &self.lcp[..]
+    }
+
+    /// Return the underlying vector, giving back ownership
+    pub fn into_vec(self) -> Vec<Index> {
+        self.lcp
+    }
+}
+
+/// Returns the number of bytes shared by `text[i..]` and `text[j..]`,
+/// knowing that the first `h` are already equal.
+#[inline(always)]
+fn extend(text: &[u8], i: usize, j: usize, mut h: usize) -> usize {
+    let n = text.len();
+    while i + h < n && j + h < n && text[i + h] == text[j + h] {
+        h += 1;
+    }
+    h
+}
+
+/// Builds the LCP array of `text` from its suffix array `sa`, in O(n),
+/// using Kasai et al.'s algorithm.
+///
+/// Needs an `n`-word rank array on top of the result.
+pub fn kasai<Index>(text: &[u8], sa: &[Index]) -> LcpArray<Index>
+where
+    Index: ToPrimitive + FromPrimitive,
+{
+    let n = sa.len();
+    assert_eq!(text.len(), n, "text and suffix array should have same len");
+
+    let mut rank = vec![0_usize; n];
+    for (i, x) in sa.iter().enumerate() {
+        rank[x.to_usize().unwrap()] = i;
+    }
+
+    let mut lcp: Vec<Index> = (0..n).map(|_| Index::from_usize(0).unwrap()).collect();
+    let mut h = 0_usize;
+    for (i, &r) in rank.iter().enumerate() {
+        if r > 0 {
+            let j = sa[r - 1].to_usize().unwrap();
+            h = extend(text, i, j, h);
+            lcp[r] = Index::from_usize(h).unwrap();
+            h = h.saturating_sub(1);
+        } else {
+            h = 0;
+        }
+    }
+    LcpArray::new(lcp)
+}
+
+/// Builds the LCP array of `text` from its suffix array `sa`, in O(n),
+/// by way of the permuted LCP array (Kärkkäinen, Manzini & Puglisi's Φ
+/// algorithm).
+///
+/// The Φ array is computed and turned into the PLCP array in place,
+/// so the only scratch space is one `Index` per suffix, which makes it
+/// a better fit than `kasai` for large inputs with a narrow `Index`.
+pub fn phi<Index>(text: &[u8], sa: &[Index]) -> LcpArray<Index>
+where
+    Index: ToPrimitive + FromPrimitive,
+{
+    let n = sa.len();
+    assert_eq!(text.len(), n, "text and suffix array should have same len");
+
+    macro_rules! idx {
+        ($x: expr) => {
+            Index::from_usize($x).unwrap()
+        };
+    }
+
+    if n == 0 {
+        return LcpArray::new(Vec::new());
+    }
+
+    // phi[sa[i]] = sa[i - 1]; the first suffix has no predecessor,
+    // and is marked with `n`.
+    let mut plcp: Vec<Index> = (0..n).map(|_| idx!(0)).collect();
+    plcp[sa[0].to_usize().unwrap()] = idx!(n);
+    for i in 1..n {
+        plcp[sa[i].to_usize().unwrap()] = idx!(sa[i - 1].to_usize().unwrap());
+    }
+
+    // turn phi into plcp, left to right
+    let mut h = 0_usize;
+    for (i, p) in plcp.iter_mut().enumerate() {
+        let j = p.to_usize().unwrap();
+        if j == n {
+            h = 0;
+        } else {
+            h = extend(text, i, j, h);
+        }
+        *p = idx!(h);
+        h = h.saturating_sub(1);
+    }
+
+    // permute plcp into lcp order
+    let lcp = sa
+        .iter()
+        .map(|x| idx!(plcp[x.to_usize().unwrap()].to_usize().unwrap()))
+        .collect();
+    LcpArray::new(lcp)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::banana, SuffixArray};
+
+    #[test]
+    fn lcp() {
+        let sa = banana();
+        assert_eq!(sa.lcp().as_slice(), &[0, 1, 3, 0, 0, 2]);
+        assert_eq!(sa.lcp_phi().as_slice(), &[0, 1, 3, 0, 0, 2]);
+
+        let text = b"mississippi";
+        let sa = SuffixArray::new(&text[..], vec![10, 7, 4, 1, 0, 9, 8, 6, 3, 5, 2]);
+        sa.verify().unwrap();
+        let expected: &[usize] = &[0, 1, 1, 4, 0, 0, 1, 0, 2, 1, 3];
+        assert_eq!(sa.lcp().as_slice(), expected);
+        assert_eq!(sa.lcp_phi().as_slice(), expected);
+    }
+}
@@ -1,5 +1,27 @@
-use num_traits::ToPrimitive;
-use std::{cmp::min, fmt};
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::{cmp::min, fmt, io, ops::Range, path::Path, slice};
+
+pub mod doclist;
+pub mod file;
+pub mod gsa;
+pub mod isa;
+pub mod joint;
+pub mod lcp;
+pub mod lz;
+pub mod mlr;
+pub mod naive;
+pub mod repeats;
+pub mod saca;
+
+pub use doclist::DocumentTable;
+pub use gsa::GeneralizedSuffixArray;
+pub use isa::InverseSuffixArray;
+pub use joint::{JointSuffixArray, Mem};
+pub use lcp::LcpArray;
+pub use lz::Phrase;
+pub use mlr::MlrTable;
+pub use repeats::Repeat;
+pub use saca::{DynSaca, Saca};
 
 pub struct LongestCommonSubstring<'a> {
     text: &'a [u8],
@@ -28,6 +50,11 @@ impl<'a> LongestCommonSubstring<'a> {
     pub fn len(&self) -> usize {
         self.len
     }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
 }
 
 /// Returns the number of bytes `a` and `b` have in common.
@@ -75,7 +102,7 @@ where
     macro_rules! lcs {
         ($start: expr, $len: expr) => {
             LongestCommonSubstring {
-                text: text,
+                text,
                 start: $start,
                 len: $len,
             }
@@ -108,6 +135,72 @@ where
     }
 }
 
+/// Returns the interval of `sa` whose suffixes all start with `needle`,
+/// in O(m log n). The interval is empty if `needle` does not occur in `text`.
+pub fn occurrence_range<Index>(text: &[u8], sa: &[Index], needle: &[u8]) -> Range<usize>
+where
+    Index: ToPrimitive,
+{
+    macro_rules! suff {
+        ($x: expr) => {
+            &text[$x.to_usize().unwrap()..]
+        };
+    }
+
+    // first suffix that is not smaller than `needle`...
+    let start = sa.partition_point(|x| suff!(x) < needle);
+    // ...and the run of suffixes after it that have `needle` as a prefix.
+    let len = sa[start..].partition_point(|x| suff!(x).starts_with(needle));
+    start..start + len
+}
+
+/// Iterator over the text positions of every occurrence of a needle,
+/// in suffix array order (not text order).
+pub struct Occurrences<'b, Index> {
+    inner: slice::Iter<'b, Index>,
+}
+
+impl<'b, Index> Iterator for Occurrences<'b, Index>
+where
+    Index: ToPrimitive,
+{
+    type Item = usize;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<usize> {
+        self.inner.next().map(|x| x.to_usize().unwrap())
+    }
+
+    #[inline(always)]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'b, Index> DoubleEndedIterator for Occurrences<'b, Index>
+where
+    Index: ToPrimitive,
+{
+    #[inline(always)]
+    fn next_back(&mut self) -> Option<usize> {
+        self.inner.next_back().map(|x| x.to_usize().unwrap())
+    }
+}
+
+impl<'b, Index> ExactSizeIterator for Occurrences<'b, Index> where Index: ToPrimitive {}
+
+/// Finds all occurrences of `needle` in `text`, using its suffix array `sa`.
+/// Positions are yielded in suffix array order, sort them if text order
+/// is needed.
+pub fn find_all<'b, Index>(text: &[u8], sa: &'b [Index], needle: &[u8]) -> Occurrences<'b, Index>
+where
+    Index: ToPrimitive,
+{
+    Occurrences {
+        inner: sa[occurrence_range(text, sa, needle)].iter(),
+    }
+}
+
 /// Error returned by `verify` when a suffix array is not sorted.
 pub struct NotSorted {
     i: usize,
@@ -146,13 +239,13 @@ where
 
     macro_rules! suff {
         ($x: expr) => {
-            &input[sa!($x)..]
+            input[sa!($x)..]
         };
     }
 
-    for i in 0..(input.len() - 1) {
-        if !(suff!(i) < suff!(i + 1)) {
-            return Err(NotSorted { i: i, j: i + 1 });
+    for i in 0..input.len().saturating_sub(1) {
+        if suff!(i) >= suff!(i + 1) {
+            return Err(NotSorted { i, j: i + 1 });
         }
     }
     Ok(())
@@ -181,6 +274,22 @@ where
         longest_substring_match(self.text, &self.sa[..], needle)
     }
 
+    /// Returns the interval of the suffix array whose suffixes start with `needle`
+    pub fn range(&self, needle: &[u8]) -> Range<usize> {
+        occurrence_range(self.text, &self.sa[..], needle)
+    }
+
+    /// Returns the number of occurrences of `needle` in the text,
+    /// without materializing their positions
+    pub fn count(&self, needle: &[u8]) -> usize {
+        self.range(needle).len()
+    }
+
+    /// Returns the positions of all occurrences of `needle` in the text
+    pub fn find_all(&self, needle: &[u8]) -> Occurrences<'_, Index> {
+        find_all(self.text, &self.sa[..], needle)
+    }
+
     /// Return (text, sa), giving back ownership of `sa`
     pub fn into_parts(self) -> (&'a [u8], Vec<Index>) {
         (self.text, self.sa)
@@ -191,10 +300,133 @@ where
     }
 }
 
+impl<'a, Index> SuffixArray<'a, Index>
+where
+    Index: ToPrimitive + FromPrimitive,
+{
+    /// Computes the inverse suffix array (rank array)
+    pub fn inverse(&self) -> InverseSuffixArray<Index> {
+        isa::inverse(&self.sa[..])
+    }
+
+    /// Builds the LCP array using Kasai's algorithm
+    pub fn lcp(&self) -> LcpArray<Index> {
+        lcp::kasai(self.text, &self.sa[..])
+    }
+
+    /// Builds the LCP array using the Φ algorithm, which needs
+    /// less scratch memory than `lcp`
+    pub fn lcp_phi(&self) -> LcpArray<Index> {
+        lcp::phi(self.text, &self.sa[..])
+    }
+
+    /// Builds the side tables needed by `range_mlr` and
+    /// `longest_substring_match_mlr`
+    pub fn mlr_table(&self) -> MlrTable<Index> {
+        MlrTable::new(&self.lcp())
+    }
+
+    /// Same as `range`, but in O(m + log n) using a table
+    /// built by `mlr_table`
+    pub fn range_mlr(&self, table: &MlrTable<Index>, needle: &[u8]) -> Range<usize> {
+        mlr::occurrence_range(self.text, &self.sa[..], table, needle)
+    }
+
+    /// Same as `longest_substring_match`, but in O(m + log n) using
+    /// a table built by `mlr_table`
+    pub fn longest_substring_match_mlr(
+        &self,
+        table: &MlrTable<Index>,
+        needle: &[u8],
+    ) -> LongestCommonSubstring<'a> {
+        mlr::longest_substring_match(self.text, &self.sa[..], table, needle)
+    }
+
+    /// Returns the LZ77 parse of the text, in O(n). It doesn't need the
+    /// LCP array.
+    pub fn lz77(&self) -> Vec<Phrase> {
+        lz::factorize(self.text, &self.sa[..])
+    }
+
+    /// Returns the longest substring occurring at least twice, given
+    /// the LCP array
+    pub fn longest_repeated_substring(&self, lcp: &LcpArray<Index>) -> Option<Repeat<'_, Index>> {
+        repeats::longest_repeated_substring(self.text, &self.sa[..], lcp)
+    }
+
+    /// Returns the maximal repeats of at least `min_len` bytes, given
+    /// the LCP array
+    pub fn maximal_repeats(&self, lcp: &LcpArray<Index>, min_len: usize) -> Vec<Repeat<'_, Index>> {
+        repeats::maximal_repeats(self.text, &self.sa[..], lcp, min_len)
+    }
+
+    /// Returns the supermaximal repeats of at least `min_len` bytes, given
+    /// the LCP array
+    pub fn supermaximal_repeats(
+        &self,
+        lcp: &LcpArray<Index>,
+        min_len: usize,
+    ) -> Vec<Repeat<'_, Index>> {
+        repeats::supermaximal_repeats(self.text, &self.sa[..], lcp, min_len)
+    }
+}
+
+impl<'a, Index> SuffixArray<'a, Index>
+where
+    Index: file::FileIndex,
+{
+    /// Saves the suffix array to `path`, see `file` for the format
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        file::save(self.text, &self.sa[..], path)
+    }
+
+    /// Loads the suffix array of `text` from `path`, into memory.
+    /// See `file::Mapped` to use it without copying.
+    pub fn load<P: AsRef<Path>>(text: &'a [u8], path: P) -> Result<Self, file::Error> {
+        file::load(text, path)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    pub(crate) fn banana() -> SuffixArray<'static, u32> {
+        SuffixArray::new(b"banana", vec![5, 3, 1, 0, 4, 2])
+    }
+
     #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+    fn find_all_occurrences() {
+        let sa = banana();
+        sa.verify().unwrap();
+
+        let mut found: Vec<usize> = sa.find_all(b"ana").collect();
+        found.sort();
+        assert_eq!(found, vec![1, 3]);
+
+        let mut found: Vec<usize> = sa.find_all(b"a").collect();
+        found.sort();
+        assert_eq!(found, vec![1, 3, 5]);
+
+        assert_eq!(sa.find_all(b"banana").collect::<Vec<_>>(), vec![0]);
+        assert_eq!(sa.find_all(b"").count(), 6);
+        assert_eq!(sa.find_all(b"nab").count(), 0);
+        assert_eq!(sa.find_all(b"bananas").count(), 0);
+        assert_eq!(sa.find_all(b"c").count(), 0);
+    }
+
+    #[test]
+    fn count_and_range() {
+        let sa = banana();
+
+        assert_eq!(sa.range(b"a"), 0..3);
+        assert_eq!(sa.range(b"ana"), 1..3);
+        assert_eq!(sa.range(b"na"), 4..6);
+        assert_eq!(sa.range(b""), 0..6);
+        assert!(sa.range(b"x").is_empty());
+
+        assert_eq!(sa.count(b"an"), 2);
+        assert_eq!(sa.count(b"b"), 1);
+        assert_eq!(sa.count(b"nab"), 0);
     }
 }
@@ -0,0 +1,160 @@
+//! LZ77 factorization, after Kärkkäinen, Kempa & Puglisi, "Linear time
+//! Lempel-Ziv factorization: simple, fast, small" (2013).
+//!
+//! The longest previous factor at position `i` starts at one of the two
+//! suffixes closest to `i` in the suffix array, on either side, among the
+//! ones starting before `i`: its previous and next smaller values (PSV
+//! and NSV). Both are found for every position with a single stack pass
+//! over the suffix array, and comparing against them costs O(n) in total,
+//! since only phrase starts are looked at.
+
+use crate::common_prefix_len;
+use num_traits::ToPrimitive;
+
+/// A phrase of the LZ77 parse
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phrase {
+    /// A byte that doesn't occur earlier in the text
+    Literal(u8),
+    /// A copy of `len` bytes from `source`, which is before the phrase
+    /// start, but may overlap it
+    Copy { source: usize, len: usize },
+}
+
+impl Phrase {
+    /// Number of bytes of text the phrase stands for
+    pub fn len(&self) -> usize {
+        match *self {
+            Phrase::Literal(_) => 1,
+            Phrase::Copy { len, .. } => len,
+        }
+    }
+
+    /// Phrases are never empty
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+const NONE: usize = usize::MAX;
+
+/// Returns the LZ77 parse of `text`, given its suffix array: every phrase
+/// is the longest prefix of the rest of the text that also starts earlier,
+/// or a literal if there's none. In O(n) time.
+pub fn factorize<Index>(text: &[u8], sa: &[Index]) -> Vec<Phrase>
+where
+    Index: ToPrimitive,
+{
+    let n = text.len();
+    assert_eq!(n, sa.len(), "text and suffix array should have same len");
+
+    let mut psv = vec![NONE; n];
+    let mut nsv = vec![NONE; n];
+    let mut stack: Vec<usize> = Vec::new();
+    for x in sa.iter().map(|x| x.to_usize().unwrap()) {
+        while let Some(&top) = stack.last() {
+            if top < x {
+                break;
+            }
+            nsv[top] = x;
+            stack.pop();
+        }
+        psv[x] = stack.last().copied().unwrap_or(NONE);
+        stack.push(x);
+    }
+    drop(stack);
+
+    let mut phrases = Vec::new();
+    let mut i = 0;
+    while i < n {
+        let mut best = (0, 0);
+        for &source in &[psv[i], nsv[i]] {
+            if source != NONE {
+                let len = common_prefix_len(&text[source..], &text[i..]);
+                if len > best.1 {
+                    best = (source, len);
+                }
+            }
+        }
+
+        let phrase = match best {
+            (_, 0) => Phrase::Literal(text[i]),
+            (source, len) => Phrase::Copy { source, len },
+        };
+        i += phrase.len();
+        phrases.push(phrase);
+    }
+    phrases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{naive::Xorshift, tests::banana, SuffixArray};
+
+    #[test]
+    fn lz77() {
+        fn naive(text: &[u8]) -> Vec<usize> {
+            let mut lens = Vec::new();
+            let mut i = 0;
+            while i < text.len() {
+                let len = (0..i)
+                    .map(|j| common_prefix_len(&text[j..], &text[i..]))
+                    .max()
+                    .unwrap_or(0);
+                lens.push(len.max(1));
+                i += len.max(1);
+            }
+            lens
+        }
+
+        let mut random = Xorshift(0x2545_f491);
+        let texts: Vec<Vec<u8>> = vec![
+            b"".to_vec(),
+            b"a".to_vec(),
+            b"aaaaaaaa".to_vec(),
+            b"abababab".to_vec(),
+            b"banana".to_vec(),
+            b"abracadabra cadabra abracadabra bra".to_vec(),
+            random.text(500, b"ab"),
+            random.text(500, b"acgt"),
+            random.text(300, b"abcdefghijklmnopqrstuvwxyz"),
+        ];
+        for text in &texts {
+            let mut sa: Vec<u32> = (0..text.len() as u32).collect();
+            sa.sort_by_key(|&i| &text[i as usize..]);
+            let phrases = SuffixArray::new(&text[..], sa).lz77();
+
+            let lens: Vec<usize> = phrases.iter().map(|p| p.len()).collect();
+            assert_eq!(lens, naive(text));
+
+            let mut decoded = Vec::new();
+            for phrase in &phrases {
+                match *phrase {
+                    Phrase::Literal(x) => {
+                        assert!(!decoded.contains(&x));
+                        decoded.push(x);
+                    }
+                    Phrase::Copy { source, len } => {
+                        assert!(source < decoded.len());
+                        for i in 0..len {
+                            decoded.push(decoded[source + i]);
+                        }
+                    }
+                }
+            }
+            assert_eq!(&decoded, text);
+        }
+
+        let phrases = banana().lz77();
+        assert_eq!(
+            phrases,
+            vec![
+                Phrase::Literal(b'b'),
+                Phrase::Literal(b'a'),
+                Phrase::Literal(b'n'),
+                Phrase::Copy { source: 1, len: 3 },
+            ]
+        );
+    }
+}
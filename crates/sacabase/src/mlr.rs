@@ -0,0 +1,231 @@
+//! Manber & Myers' "mlr" search: a binary search over the suffix array
+//! that remembers how much of the needle is already matched against
+//! both ends of the current interval, so every byte of the needle
+//! is compared at most once, for O(m + log n) searches.
+
+use crate::{common_prefix_len, LcpArray, LongestCommonSubstring};
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::{cmp::min, ops::Range};
+
+/// The LLCP/RLCP side tables for mlr search.
+///
+/// The binary search is done over positions `0..=n+1`, where position `p`
+/// stands for `sa[p - 1]`, and positions `0` and `n + 1` are virtual
+/// suffixes that share nothing with anyone. For every midpoint `mid` of
+/// the interval `(l, r)` the search can visit, `llcp[mid]` holds the LCP
+/// of positions `l` and `mid`, and `rlcp[mid]` that of `mid` and `r`.
+pub struct MlrTable<Index> {
+    llcp: Vec<Index>,
+    rlcp: Vec<Index>,
+}
+
+impl<Index> MlrTable<Index>
+where
+    Index: ToPrimitive + FromPrimitive,
+{
+    /// Builds the side tables from an LCP array, in O(n)
+    pub fn new(lcp: &LcpArray<Index>) -> Self {
+        let n = lcp.len();
+        let mut table = Self {
+            llcp: (0..n + 2).map(|_| Index::from_usize(0).unwrap()).collect(),
+            rlcp: (0..n + 2).map(|_| Index::from_usize(0).unwrap()).collect(),
+        };
+        if n > 0 {
+            table.fill(lcp, 0, n + 1);
+        }
+        table
+    }
+
+    /// Fills the tables for the interval `(l, r)`, returns the LCP
+    /// of positions `l` and `r`.
+    fn fill(&mut self, lcp: &LcpArray<Index>, l: usize, r: usize) -> usize {
+        if r - l == 1 {
+            return if l == 0 || r == lcp.len() + 1 {
+                0
+            } else {
+                lcp.get(r - 1)
+            };
+        }
+
+        let mid = (l + r) / 2;
+        let a = self.fill(lcp, l, mid);
+        let b = self.fill(lcp, mid, r);
+        self.llcp[mid] = Index::from_usize(a).unwrap();
+        self.rlcp[mid] = Index::from_usize(b).unwrap();
+        min(a, b)
+    }
+}
+
+/// Outcome of a search: `l` and `r` are adjacent positions, with
+/// the needle's matched lengths against each.
+struct Bracket {
+    l: usize,
+    r: usize,
+    lcp_l: usize,
+    lcp_r: usize,
+}
+
+/// Binary search for the boundary between suffixes that sort before
+/// `needle` and the others. With `upper` unset, suffixes starting with
+/// `needle` end up on the right side, otherwise on the left side.
+fn search<Index>(
+    text: &[u8],
+    sa: &[Index],
+    table: &MlrTable<Index>,
+    needle: &[u8],
+    upper: bool,
+) -> Bracket
+where
+    Index: ToPrimitive,
+{
+    assert_eq!(
+        table.llcp.len(),
+        sa.len() + 2,
+        "mlr table should be built for this suffix array"
+    );
+
+    // Given `h` = lcp(needle, suffix at `s`), is that suffix left
+    // of the boundary?
+    let goes_left = |s: usize, h: usize| {
+        if h == needle.len() {
+            upper
+        } else {
+            s + h == text.len() || text[s + h] < needle[h]
+        }
+    };
+
+    let mut b = Bracket {
+        l: 0,
+        r: sa.len() + 1,
+        lcp_l: 0,
+        lcp_r: 0,
+    };
+
+    while b.r - b.l > 1 {
+        let mid = (b.l + b.r) / 2;
+
+        let known = if b.lcp_l >= b.lcp_r {
+            let m = table.llcp[mid].to_usize().unwrap();
+            if m > b.lcp_l {
+                // agrees with `l` for longer than the needle does
+                b.l = mid;
+                continue;
+            } else if m < b.lcp_l {
+                // diverges from `l` (and the needle) upwards
+                b.r = mid;
+                b.lcp_r = m;
+                continue;
+            }
+            b.lcp_l
+        } else {
+            let m = table.rlcp[mid].to_usize().unwrap();
+            if m > b.lcp_r {
+                b.r = mid;
+                continue;
+            } else if m < b.lcp_r {
+                b.l = mid;
+                b.lcp_l = m;
+                continue;
+            }
+            b.lcp_r
+        };
+
+        let s = sa[mid - 1].to_usize().unwrap();
+        let h = known + common_prefix_len(&text[s + known..], &needle[known..]);
+        if goes_left(s, h) {
+            b.l = mid;
+            b.lcp_l = h;
+        } else {
+            b.r = mid;
+            b.lcp_r = h;
+        }
+    }
+    b
+}
+
+/// Returns the interval of `sa` whose suffixes all start with `needle`,
+/// in O(m + log n). Same result as `occurrence_range`.
+pub fn occurrence_range<Index>(
+    text: &[u8],
+    sa: &[Index],
+    table: &MlrTable<Index>,
+    needle: &[u8],
+) -> Range<usize>
+where
+    Index: ToPrimitive,
+{
+    let start = search(text, sa, table, needle, false).l;
+    let end = search(text, sa, table, needle, true).l;
+    start..end
+}
+
+/// Searches for the longest substring match for `needle` in `text`,
+/// in O(m + log n). Same result as `longest_substring_match`.
+pub fn longest_substring_match<'a, Index>(
+    text: &'a [u8],
+    sa: &[Index],
+    table: &MlrTable<Index>,
+    needle: &[u8],
+) -> LongestCommonSubstring<'a>
+where
+    Index: ToPrimitive,
+{
+    let b = search(text, sa, table, needle, false);
+
+    // the best match is right next to where the needle would be inserted
+    let (pos, len) = if b.l == 0 || (b.r <= sa.len() && b.lcp_r >= b.lcp_l) {
+        (b.r, b.lcp_r)
+    } else {
+        (b.l, b.lcp_l)
+    };
+    LongestCommonSubstring {
+        text,
+        start: if pos == 0 || pos > sa.len() {
+            0
+        } else {
+            sa[pos - 1].to_usize().unwrap()
+        },
+        len,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{naive::Xorshift, SuffixArray};
+
+    #[test]
+    fn mlr_search() {
+        // a deterministic pseudo-random text over a small alphabet,
+        // so there are plenty of repeats
+        let text = Xorshift(0x2545_f491).text(300, b"abc");
+        let mut sa: Vec<u32> = (0..text.len() as u32).collect();
+        sa.sort_by(|&a, &b| text[a as usize..].cmp(&text[b as usize..]));
+        let sa = SuffixArray::new(&text[..], sa);
+        let table = sa.mlr_table();
+
+        let mut needles: Vec<&[u8]> = vec![b"", b"d", b"abd", b"cccccccccccc"];
+        for i in (0..text.len()).step_by(7) {
+            for len in &[1, 2, 5, 9, 30] {
+                needles.push(&text[i..min(i + len, text.len())]);
+            }
+        }
+        needles.push(&text[..]);
+        needles.push(&text[text.len() - 1..]);
+
+        for needle in needles {
+            assert_eq!(sa.range_mlr(&table, needle), sa.range(needle));
+
+            let lcs = sa.longest_substring_match_mlr(&table, needle);
+            assert_eq!(lcs.len(), sa.longest_substring_match(needle).len());
+            assert_eq!(lcs.as_bytes(), &needle[..lcs.len()]);
+        }
+
+        let longer: Vec<u8> = text.iter().chain(b"a").cloned().collect();
+        assert_eq!(sa.range_mlr(&table, &longer).len(), 0);
+        assert_eq!(
+            sa.longest_substring_match_mlr(&table, &longer).len(),
+            text.len()
+        );
+    }
+}
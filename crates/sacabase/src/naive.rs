@@ -0,0 +1,130 @@
+//! Slow but obviously correct reference implementations, to test suffix
+//! array construction algorithms and searches against.
+//!
+//! Nothing here uses a suffix array for searching: `find_all` and
+//! `longest_match` scan the whole text.
+
+use crate::{common_prefix_len, LongestCommonSubstring, Saca, SuffixArray};
+
+/// Sorts suffixes by comparing them whole, in O(n² log n) worst case
+pub fn sort(text: &[u8]) -> SuffixArray<'_, usize> {
+    let mut sa = vec![0; text.len()];
+    Naive.sort_in_place(text, &mut sa);
+    SuffixArray::new(text, sa)
+}
+
+/// The comparison sort, as a `Saca`
+pub struct Naive;
+
+impl Saca for Naive {
+    type Index = usize;
+
+    fn name(&self) -> &'static str {
+        "naive"
+    }
+
+    fn max_len(&self) -> usize {
+        usize::MAX
+    }
+
+    fn sort_in_place(&self, text: &[u8], sa: &mut [usize]) {
+        assert_eq!(
+            text.len(),
+            sa.len(),
+            "text and suffix array should have same len"
+        );
+        for (i, x) in sa.iter_mut().enumerate() {
+            *x = i;
+        }
+        sa.sort_by_key(|&i| &text[i..]);
+    }
+}
+
+/// Returns every position `needle` occurs at in `text`, in text order.
+/// The empty needle occurs at every position.
+pub fn find_all(text: &[u8], needle: &[u8]) -> Vec<usize> {
+    (0..text.len())
+        .filter(|&i| text[i..].starts_with(needle))
+        .collect()
+}
+
+/// Returns the longest prefix of `needle` occurring in `text`, at its
+/// first position. Searches on a suffix array may return any position
+/// of a match of the same length.
+pub fn longest_match<'a>(text: &'a [u8], needle: &[u8]) -> LongestCommonSubstring<'a> {
+    let mut best = LongestCommonSubstring {
+        text,
+        start: 0,
+        len: 0,
+    };
+    for i in 0..text.len() {
+        let len = common_prefix_len(&text[i..], needle);
+        if len > best.len {
+            best.start = i;
+            best.len = len;
+        }
+    }
+    best
+}
+
+/// Xorshift generator, to make reproducible random texts in tests.
+/// The seed must not be zero. Only built for tests, and with the
+/// `test-support` feature, for the tests of the other crates here: it's
+/// not part of the API.
+#[cfg(any(test, feature = "test-support"))]
+#[doc(hidden)]
+pub struct Xorshift(pub u32);
+
+#[cfg(any(test, feature = "test-support"))]
+impl Xorshift {
+    pub fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// Returns `len` bytes picked from `alphabet`
+    pub fn text(&mut self, len: usize, alphabet: &[u8]) -> Vec<u8> {
+        (0..len)
+            .map(|_| alphabet[self.next_u32() as usize % alphabet.len()])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::naive;
+
+    #[test]
+    fn naive() {
+        let sa = naive::sort(b"banana");
+        sa.verify().unwrap();
+        assert_eq!(sa.into_parts().1, vec![5, 3, 1, 0, 4, 2]);
+        assert_eq!(naive::find_all(b"banana", b"ana"), vec![1, 3]);
+        assert_eq!(naive::find_all(b"banana", b"").len(), 6);
+        assert_eq!(naive::find_all(b"", b""), Vec::<usize>::new());
+        let m = naive::longest_match(b"banana", b"nanas");
+        assert_eq!((m.start(), m.as_bytes()), (2, &b"nana"[..]));
+        assert!(naive::longest_match(b"", b"a").is_empty());
+
+        // searches against the oracles
+        let mut random = naive::Xorshift(0x2545_f491);
+        for len in 1..40 {
+            let text = random.text(len, b"ab");
+            let sa = naive::sort(&text);
+            sa.verify().unwrap();
+            for needle_len in 0..6 {
+                let needle = random.text(needle_len, b"abc");
+                let mut found: Vec<usize> = sa.find_all(&needle).collect();
+                found.sort_unstable();
+                assert_eq!(found, naive::find_all(&text, &needle));
+                assert_eq!(sa.count(&needle), found.len());
+
+                let m = sa.longest_substring_match(&needle);
+                assert_eq!(m.len(), naive::longest_match(&text, &needle).len());
+                assert_eq!(m.as_bytes(), &needle[..m.len()]);
+            }
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Repeats, found by a bottom-up traversal of the LCP intervals, after
+//! Abouelhoda, Kurtz & Ohlebusch, "Replacing suffix trees with enhanced
+//! suffix arrays" (2004).
+//!
+//! An LCP interval of value `ℓ` is a maximal range of suffix array rows
+//! sharing a prefix of length `ℓ`, that is an internal node of the suffix
+//! tree. Its prefix is right-maximal: it's followed by at least two
+//! different bytes. It's a maximal repeat if it's also left-maximal: its
+//! occurrences aren't all preceded by the same byte.
+
+use crate::{LcpArray, Occurrences};
+use num_traits::ToPrimitive;
+use std::{fmt, ops::Range};
+
+/// A substring occurring at least twice
+pub struct Repeat<'a, Index> {
+    text: &'a [u8],
+    sa: &'a [Index],
+    len: usize,
+    rows: Range<usize>,
+}
+
+impl<'a, Index> fmt::Debug for Repeat<'a, Index>
+where
+    Index: ToPrimitive,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let start = self.sa[self.rows.start].to_usize().unwrap();
+        write!(f, "T[{}..{}] x{}", start, start + self.len, self.rows.len())
+    }
+}
+
+impl<'a, Index> Repeat<'a, Index>
+where
+    Index: ToPrimitive,
+{
+    pub fn as_bytes(&self) -> &'a [u8] {
+        let start = self.sa[self.rows.start].to_usize().unwrap();
+        &self.text[start..start + self.len]
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Interval of the suffix array whose suffixes start with the repeat
+    pub fn rows(&self) -> Range<usize> {
+        self.rows.clone()
+    }
+
+    /// Number of occurrences
+    pub fn count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Positions of all occurrences, in suffix array order
+    pub fn positions(&self) -> Occurrences<'a, Index> {
+        Occurrences {
+            inner: self.sa[self.rows.clone()].iter(),
+        }
+    }
+}
+
+/// Byte before a suffix, 256 for the suffix starting the text,
+/// which is preceded by nothing else
+#[inline(always)]
+fn left<Index: ToPrimitive>(text: &[u8], sa: &[Index], row: usize) -> u16 {
+    match sa[row].to_usize().unwrap() {
+        0 => 256,
+        pos => text[pos - 1] as u16,
+    }
+}
+
+/// An LCP interval on the traversal stack
+struct Interval {
+    lcp: usize,
+    lb: usize,
+    /// The byte before all of its suffixes, or `None` if they differ
+    left: Option<u16>,
+    /// Whether it has child intervals
+    internal: bool,
+}
+
+fn merge(a: Option<u16>, b: Option<u16>) -> Option<u16> {
+    if a == b {
+        a
+    } else {
+        None
+    }
+}
+
+/// Calls `visit(lcp, rows, left_maximal, internal)` for every LCP interval
+/// but the root, children before their parents
+fn lcp_intervals<Index>(
+    text: &[u8],
+    sa: &[Index],
+    lcp: &LcpArray<Index>,
+    mut visit: impl FnMut(usize, Range<usize>, bool, bool),
+) where
+    Index: ToPrimitive,
+{
+    let n = sa.len();
+    assert_eq!(
+        n,
+        lcp.len(),
+        "suffix array and LCP array should have same len"
+    );
+    if n == 0 {
+        return;
+    }
+
+    let mut stack = vec![Interval {
+        lcp: 0,
+        lb: 0,
+        left: Some(left(text, sa, 0)),
+        internal: false,
+    }];
+    for i in 1..=n {
+        let cur = if i < n { lcp.get(i) } else { 0 };
+        let mut lb = i - 1;
+        let mut popped: Option<Interval> = None;
+        while cur < stack.last().unwrap().lcp {
+            let node = stack.pop().unwrap();
+            visit(node.lcp, node.lb..i, node.left.is_none(), node.internal);
+            lb = node.lb;
+            let top = stack.last_mut().unwrap();
+            if cur <= top.lcp {
+                // `node` is a child of `top`
+                top.left = merge(top.left, node.left);
+                top.internal = true;
+            } else {
+                popped = Some(node);
+            }
+        }
+        if cur > stack.last().unwrap().lcp {
+            // a new interval, holding row i - 1 or the last popped one
+            let (left, internal) = match popped {
+                Some(child) => (child.left, true),
+                None => (Some(left(text, sa, i - 1)), false),
+            };
+            stack.push(Interval {
+                lcp: cur,
+                lb,
+                left,
+                internal,
+            });
+        }
+        if i < n {
+            let top = stack.last_mut().unwrap();
+            top.left = merge(top.left, Some(left(text, sa, i)));
+        }
+    }
+}
+
+/// Returns the longest substring occurring at least twice, with all its
+/// occurrences, or `None` if there are no repeats
+pub fn longest_repeated_substring<'a, Index>(
+    text: &'a [u8],
+    sa: &'a [Index],
+    lcp: &LcpArray<Index>,
+) -> Option<Repeat<'a, Index>>
+where
+    Index: ToPrimitive,
+{
+    let (best, len) = (1..lcp.len())
+        .map(|i| (i, lcp.get(i)))
+        .max_by_key(|&(i, len)| (len, std::cmp::Reverse(i)))?;
+    if len == 0 {
+        return None;
+    }
+
+    let mut end = best + 1;
+    while end < lcp.len() && lcp.get(end) >= len {
+        end += 1;
+    }
+    Some(Repeat {
+        text,
+        sa,
+        len,
+        rows: best - 1..end,
+    })
+}
+
+/// Returns every maximal repeat of at least `min_len` bytes: repeats
+/// that can't be extended to the left or to the right without losing
+/// an occurrence. Shorter repeats come before the longer ones they're
+/// a prefix of, otherwise the order is unspecified.
+pub fn maximal_repeats<'a, Index>(
+    text: &'a [u8],
+    sa: &'a [Index],
+    lcp: &LcpArray<Index>,
+    min_len: usize,
+) -> Vec<Repeat<'a, Index>>
+where
+    Index: ToPrimitive,
+{
+    let mut repeats = Vec::new();
+    lcp_intervals(text, sa, lcp, |len, rows, left_maximal, _| {
+        if left_maximal && len >= min_len.max(1) {
+            repeats.push(Repeat {
+                text,
+                sa,
+                len,
+                rows,
+            });
+        }
+    });
+    repeats.reverse();
+    repeats
+}
+
+/// Returns every supermaximal repeat of at least `min_len` bytes:
+/// maximal repeats that aren't a substring of another repeat, sorted by
+/// suffix array interval.
+pub fn supermaximal_repeats<'a, Index>(
+    text: &'a [u8],
+    sa: &'a [Index],
+    lcp: &LcpArray<Index>,
+    min_len: usize,
+) -> Vec<Repeat<'a, Index>>
+where
+    Index: ToPrimitive,
+{
+    // Those are the intervals without child intervals whose occurrences
+    // are all preceded by different bytes.
+    let mut repeats = Vec::new();
+    let mut lefts = Vec::new();
+    lcp_intervals(text, sa, lcp, |len, rows, _, internal| {
+        if internal || len < min_len.max(1) {
+            return;
+        }
+        lefts.clear();
+        lefts.extend(rows.clone().map(|row| left(text, sa, row)));
+        lefts.sort_unstable();
+        if lefts.windows(2).all(|w| w[0] != w[1]) {
+            repeats.push(Repeat {
+                text,
+                sa,
+                len,
+                rows,
+            });
+        }
+    });
+    repeats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests::banana, SuffixArray};
+
+    #[test]
+    fn repeats() {
+        let sa = banana();
+        let lcp = sa.lcp();
+        let lrs = sa.longest_repeated_substring(&lcp).unwrap();
+        assert_eq!(lrs.as_bytes(), b"ana");
+        let mut found: Vec<usize> = lrs.positions().collect();
+        found.sort();
+        assert_eq!(found, vec![1, 3]);
+
+        let bytes = |repeats: Vec<Repeat<'_, u32>>| -> Vec<Vec<u8>> {
+            let mut found: Vec<Vec<u8>> = repeats.iter().map(|r| r.as_bytes().to_vec()).collect();
+            found.sort();
+            found
+        };
+        // "an" and "na" always extend to "ana"
+        assert_eq!(
+            bytes(sa.maximal_repeats(&lcp, 0)),
+            vec![b"a".to_vec(), b"ana".to_vec()]
+        );
+        assert_eq!(bytes(sa.maximal_repeats(&lcp, 2)), vec![b"ana".to_vec()]);
+        assert_eq!(
+            bytes(sa.supermaximal_repeats(&lcp, 0)),
+            vec![b"ana".to_vec()]
+        );
+
+        let none = SuffixArray::new(b"abc", vec![0_u32, 1, 2]);
+        let lcp = none.lcp();
+        assert!(none.longest_repeated_substring(&lcp).is_none());
+        assert!(none.maximal_repeats(&lcp, 0).is_empty());
+
+        // against brute force, on a text with many repeats
+        let text = b"abracadabra cadabra abracadabra bra";
+        let mut sa: Vec<u32> = (0..text.len() as u32).collect();
+        sa.sort_by_key(|&i| &text[i as usize..]);
+        let sa = SuffixArray::new(&text[..], sa);
+        let lcp = sa.lcp();
+
+        let count = |s: &[u8]| {
+            (0..text.len())
+                .filter(|&i| text[i..].starts_with(s))
+                .count()
+        };
+        let mut maximal = Vec::new();
+        for i in 0..text.len() {
+            for j in i + 1..=text.len() {
+                let s = &text[i..j];
+                let k = count(s);
+                if k < 2 || maximal.contains(&s.to_vec()) {
+                    continue;
+                }
+                let extends = |t: Vec<u8>| count(&t) == k;
+                let left = (0..=255).any(|x| extends([&[x], s].concat()));
+                let right = (0..=255).any(|x| extends([s, &[x]].concat()));
+                if !left && !right {
+                    maximal.push(s.to_vec());
+                }
+            }
+        }
+        maximal.sort();
+        assert_eq!(bytes(sa.maximal_repeats(&lcp, 0)), maximal);
+
+        let supermaximal: Vec<Vec<u8>> = maximal
+            .iter()
+            .filter(|s| {
+                !maximal
+                    .iter()
+                    .any(|t| t.len() > s.len() && t.windows(s.len()).any(|w| w == &s[..]))
+            })
+            .cloned()
+            .collect();
+        assert_eq!(bytes(sa.supermaximal_repeats(&lcp, 0)), supermaximal);
+        assert_eq!(
+            bytes(sa.supermaximal_repeats(&lcp, 5)),
+            supermaximal
+                .into_iter()
+                .filter(|s| s.len() >= 5)
+                .collect::<Vec<_>>()
+        );
+        let lrs = sa.longest_repeated_substring(&lcp).unwrap();
+        assert_eq!(lrs.as_bytes(), b"abracadabra ");
+        assert_eq!(lrs.count(), 2);
+    }
+}
@@ -0,0 +1,108 @@
+//! A common interface for suffix array construction algorithms, so they
+//! can be swapped for one another, or picked at runtime.
+
+use crate::SuffixArray;
+use num_traits::{FromPrimitive, ToPrimitive};
+use std::time::{Duration, Instant};
+
+/// A suffix array construction algorithm
+pub trait Saca {
+    /// Index type of the suffix arrays it builds
+    type Index: ToPrimitive + FromPrimitive;
+
+    /// Short name, as used on the command line and in benchmarks
+    fn name(&self) -> &'static str;
+
+    /// Longest input it can sort, in bytes
+    fn max_len(&self) -> usize;
+
+    /// Name of the index type
+    fn index_type(&self) -> &'static str {
+        std::any::type_name::<Self::Index>()
+    }
+
+    /// Sort suffixes of `text` and store their lexographic order
+    /// in the given suffix array `sa`.
+    /// Will panic if `sa.len()` != `text.len()`, or if `text` is
+    /// longer than `max_len()`.
+    fn sort_in_place(&self, text: &[u8], sa: &mut [Self::Index]);
+
+    /// Sort suffixes
+    fn sort<'a>(&self, text: &'a [u8]) -> SuffixArray<'a, Self::Index> {
+        let mut sa: Vec<Self::Index> = (0..text.len())
+            .map(|_| Self::Index::from_usize(0).unwrap())
+            .collect();
+        self.sort_in_place(text, &mut sa);
+        SuffixArray::new(text, sa)
+    }
+}
+
+/// Object-safe counterpart of `Saca`, implemented by every `Saca`,
+/// for picking algorithms with different index types at runtime.
+pub trait DynSaca {
+    /// See `Saca::name`
+    fn name(&self) -> &'static str;
+
+    /// See `Saca::max_len`
+    fn max_len(&self) -> usize;
+
+    /// See `Saca::index_type`
+    fn index_type(&self) -> &'static str;
+
+    /// Sort suffixes, converting the result to `usize` indices
+    fn sort_usize<'a>(&self, text: &'a [u8]) -> SuffixArray<'a, usize>;
+
+    /// Sort suffixes into native indices, allocated beforehand, and
+    /// return how long sorting took, for benchmarks
+    fn sort_timed(&self, text: &[u8]) -> Duration;
+}
+
+impl<S> DynSaca for S
+where
+    S: Saca,
+{
+    fn name(&self) -> &'static str {
+        Saca::name(self)
+    }
+
+    fn max_len(&self) -> usize {
+        Saca::max_len(self)
+    }
+
+    fn index_type(&self) -> &'static str {
+        Saca::index_type(self)
+    }
+
+    fn sort_usize<'a>(&self, text: &'a [u8]) -> SuffixArray<'a, usize> {
+        let (text, sa) = Saca::sort(self, text).into_parts();
+        SuffixArray::new(text, sa.iter().map(|x| x.to_usize().unwrap()).collect())
+    }
+
+    fn sort_timed(&self, text: &[u8]) -> Duration {
+        let mut sa: Vec<S::Index> = (0..text.len())
+            .map(|_| S::Index::from_usize(0).unwrap())
+            .collect();
+        let before = Instant::now();
+        self.sort_in_place(text, &mut sa);
+        before.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::naive::Naive;
+
+    #[test]
+    fn saca() {
+        let (_, sa) = Naive.sort(b"banana").into_parts();
+        assert_eq!(sa, vec![5, 3, 1, 0, 4, 2]);
+
+        let algorithms: Vec<Box<dyn DynSaca>> = vec![Box::new(Naive)];
+        let naive = algorithms.iter().find(|a| a.name() == "naive").unwrap();
+        assert_eq!(naive.index_type(), "usize");
+        assert_eq!(naive.max_len(), usize::MAX);
+        let (_, sa) = naive.sort_usize(b"banana").into_parts();
+        assert_eq!(sa, vec![5, 3, 1, 0, 4, 2]);
+    }
+}
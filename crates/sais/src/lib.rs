@@ -0,0 +1,429 @@
+//! SA-IS: suffix sorting by induced sorting, in linear time.
+//!
+//! The text is followed by a virtual sentinel, smaller than every symbol,
+//! which is never stored.
+
+/// Index type of the suffix arrays built by this crate
+pub type Idx = u32;
+
+/// Marks empty slots of the suffix array while inducing
+const EMPTY: Idx = Idx::MAX;
+
+/// A symbol of an integer alphabet
+pub trait Symbol: Copy + Eq {
+    /// Rank of the symbol in the alphabet, must be less than
+    /// the alphabet size
+    fn rank(self) -> usize;
+}
+
+macro_rules! impl_symbol {
+    ($($t: ty),*) => {
+        $(
+            impl Symbol for $t {
+                #[inline(always)]
+                fn rank(self) -> usize {
+                    self as usize
+                }
+            }
+        )*
+    };
+}
+
+impl_symbol!(u8, u16, u32, usize);
+
+/// Sort suffixes of `text` and store their lexographic order
+/// in the given suffix array `sa`.
+/// Will panic if `sa.len()` != `text.len()`
+pub fn sort_in_place(text: &[u8], sa: &mut [Idx]) {
+    suffix_array(text, sa, 256);
+}
+
+/// Sort suffixes
+pub fn sort(text: &[u8]) -> sacabase::SuffixArray<'_, Idx> {
+    let mut sa = vec![0; text.len()];
+    sort_in_place(text, &mut sa);
+    sacabase::SuffixArray::new(text, sa)
+}
+
+/// SA-IS, as a `sacabase::Saca`
+pub struct Sais;
+
+impl sacabase::Saca for Sais {
+    type Index = Idx;
+
+    fn name(&self) -> &'static str {
+        "sais"
+    }
+
+    fn max_len(&self) -> usize {
+        EMPTY as usize - 1
+    }
+
+    fn sort_in_place(&self, text: &[u8], sa: &mut [Idx]) {
+        sort_in_place(text, sa)
+    }
+}
+
+/// Sort suffixes of `text`, whose symbols all rank below `k`,
+/// into `sa`. Will panic if `sa.len()` != `text.len()`, or if
+/// a symbol ranks `k` or more.
+pub fn suffix_array<C: Symbol>(text: &[C], sa: &mut [Idx], k: usize) {
+    assert_eq!(
+        text.len(),
+        sa.len(),
+        "text and suffix array should have same len"
+    );
+    assert!(
+        text.len() < EMPTY as usize,
+        "text too large, should not exceed {} symbols",
+        EMPTY - 1
+    );
+    sais(text, sa, k);
+}
+
+/// Suffix types: `stype[i]` is true if suffix `i` is smaller than
+/// suffix `i + 1` (S-type), false otherwise (L-type).
+struct Types(Vec<bool>);
+
+impl Types {
+    fn new<C: Symbol>(text: &[C]) -> Self {
+        let n = text.len();
+        // the last suffix is followed by the sentinel, so it's L-type
+        let mut stype = vec![false; n];
+        for i in (0..n - 1).rev() {
+            stype[i] =
+                text[i].rank() < text[i + 1].rank() || (text[i] == text[i + 1] && stype[i + 1]);
+        }
+        Self(stype)
+    }
+
+    #[inline(always)]
+    fn is_s(&self, i: usize) -> bool {
+        self.0[i]
+    }
+
+    /// Is suffix `i` the leftmost S-type suffix of its run?
+    #[inline(always)]
+    fn is_lms(&self, i: usize) -> bool {
+        i > 0 && self.0[i] && !self.0[i - 1]
+    }
+}
+
+/// Per-symbol bucket boundaries
+struct Buckets(Vec<Idx>);
+
+impl Buckets {
+    fn count<C: Symbol>(text: &[C], k: usize) -> Vec<Idx> {
+        let mut counts = vec![0; k];
+        for c in text {
+            counts[c.rank()] += 1;
+        }
+        counts
+    }
+
+    /// Start of every bucket
+    fn heads(counts: &[Idx]) -> Self {
+        let mut sum = 0;
+        Self(
+            counts
+                .iter()
+                .map(|&c| {
+                    sum += c;
+                    sum - c
+                })
+                .collect(),
+        )
+    }
+
+    /// End (exclusive) of every bucket
+    fn tails(counts: &[Idx]) -> Self {
+        let mut sum = 0;
+        Self(
+            counts
+                .iter()
+                .map(|&c| {
+                    sum += c;
+                    sum
+                })
+                .collect(),
+        )
+    }
+
+    /// Reserves the next slot at the start of bucket `c`
+    #[inline(always)]
+    fn push_front(&mut self, c: usize) -> usize {
+        let i = self.0[c];
+        self.0[c] += 1;
+        i as usize
+    }
+
+    /// Reserves the next slot at the end of bucket `c`
+    #[inline(always)]
+    fn push_back(&mut self, c: usize) -> usize {
+        self.0[c] -= 1;
+        self.0[c] as usize
+    }
+}
+
+/// Induces the order of L-type suffixes, then of S-type suffixes, from
+/// the LMS suffixes already placed at the end of their buckets.
+fn induce<C: Symbol>(text: &[C], sa: &mut [Idx], types: &Types, counts: &[Idx]) {
+    let n = text.len();
+
+    let mut heads = Buckets::heads(counts);
+    // the sentinel comes first, and is preceded by the last suffix
+    sa[heads.push_front(text[n - 1].rank())] = (n - 1) as Idx;
+    for i in 0..n {
+        let j = sa[i];
+        if j != EMPTY && j > 0 && !types.is_s(j as usize - 1) {
+            let j = j as usize - 1;
+            sa[heads.push_front(text[j].rank())] = j as Idx;
+        }
+    }
+
+    let mut tails = Buckets::tails(counts);
+    for i in (0..n).rev() {
+        let j = sa[i];
+        if j != EMPTY && j > 0 && types.is_s(j as usize - 1) {
+            let j = j as usize - 1;
+            sa[tails.push_back(text[j].rank())] = j as Idx;
+        }
+    }
+}
+
+/// Are the LMS substrings starting at `a` and `b` equal?
+fn lms_equal<C: Symbol>(text: &[C], types: &Types, a: usize, b: usize) -> bool {
+    let n = text.len();
+    for d in 0.. {
+        if a + d == n || b + d == n {
+            // only one of them can reach the (unique) sentinel
+            return false;
+        }
+        if text[a + d] != text[b + d] || types.is_s(a + d) != types.is_s(b + d) {
+            return false;
+        }
+        if d > 0 && (types.is_lms(a + d) || types.is_lms(b + d)) {
+            return types.is_lms(a + d) && types.is_lms(b + d);
+        }
+    }
+    unreachable!()
+}
+
+fn sais<C: Symbol>(text: &[C], sa: &mut [Idx], k: usize) {
+    let n = text.len();
+    match n {
+        0 => return,
+        1 => {
+            sa[0] = 0;
+            return;
+        }
+        _ => {}
+    }
+
+    let types = Types::new(text);
+    let counts = Buckets::count(text, k);
+
+    // Stage 1: sort LMS substrings, by inducing from LMS suffixes
+    // placed in any order.
+    sa.iter_mut().for_each(|x| *x = EMPTY);
+    let mut tails = Buckets::tails(&counts);
+    for i in (1..n).rev() {
+        if types.is_lms(i) {
+            sa[tails.push_back(text[i].rank())] = i as Idx;
+        }
+    }
+    induce(text, sa, &types, &counts);
+
+    // Move the sorted LMS substrings to the front
+    let mut m = 0;
+    for i in 0..n {
+        let j = sa[i] as usize;
+        if types.is_lms(j) {
+            sa[m] = j as Idx;
+            m += 1;
+        }
+    }
+
+    // Name them; LMS positions are at least two apart, so names can be
+    // stored at `m + pos / 2` without collisions.
+    sa[m..].iter_mut().for_each(|x| *x = EMPTY);
+    let mut names = 0;
+    let mut prev: Option<usize> = None;
+    for i in 0..m {
+        let pos = sa[i] as usize;
+        if !prev.map_or(false, |p| lms_equal(text, &types, p, pos)) {
+            names += 1;
+        }
+        prev = Some(pos);
+        sa[m + pos / 2] = (names - 1) as Idx;
+    }
+
+    // Gather the names, in text order, at the end: that's the reduced string.
+    let mut j = n;
+    for i in (m..n).rev() {
+        if sa[i] != EMPTY {
+            j -= 1;
+            sa[j] = sa[i];
+        }
+    }
+
+    // Stage 2: sort the LMS suffixes, through the reduced string.
+    // There are at most n / 2 of them, so both halves fit.
+    {
+        let (front, reduced) = sa.split_at_mut(n - m);
+        let sa1 = &mut front[..m];
+        if names < m {
+            sais(&reduced[..], sa1, names);
+        } else {
+            // all names are unique, invert directly
+            for (i, &name) in reduced.iter().enumerate() {
+                sa1[name as usize] = i as Idx;
+            }
+        }
+
+        // map ranks in the reduced string back to text positions
+        let mut j = 0;
+        for i in 1..n {
+            if types.is_lms(i) {
+                reduced[j] = i as Idx;
+                j += 1;
+            }
+        }
+        for x in sa1.iter_mut() {
+            *x = reduced[*x as usize];
+        }
+    }
+
+    // Stage 3: induce the whole order from the sorted LMS suffixes
+    sa[m..].iter_mut().for_each(|x| *x = EMPTY);
+    let mut tails = Buckets::tails(&counts);
+    for i in (0..m).rev() {
+        let j = sa[i] as usize;
+        sa[i] = EMPTY;
+        sa[tails.push_back(text[j].rank())] = j as Idx;
+    }
+    induce(text, sa, &types, &counts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sacabase::naive::{self, Xorshift};
+
+    /// Same as `naive::sort`, for symbols other than bytes
+    fn naive_symbols<C: Symbol + Ord>(text: &[C]) -> Vec<Idx> {
+        let mut sa: Vec<Idx> = (0..text.len() as Idx).collect();
+        sa.sort_by_key(|&i| &text[i as usize..]);
+        sa
+    }
+
+    fn naive(text: &[u8]) -> Vec<Idx> {
+        let (_, sa) = naive::sort(text).into_parts();
+        sa.into_iter().map(|i| i as Idx).collect()
+    }
+
+    fn crosscheck(s: &[u8]) {
+        let (_, sa) = super::sort(s).into_parts();
+        let (_, expected) = divsufsort::sort(s).into_parts();
+        assert!(
+            sa.len() == expected.len()
+                && sa.iter().zip(expected.iter()).all(|(&a, &b)| a == b as Idx),
+            "sais and divsufsort disagree on {:?}",
+            s
+        );
+    }
+
+    #[test]
+    fn small() {
+        for s in &[
+            "",
+            "a",
+            "ab",
+            "ba",
+            "aa",
+            "aaa",
+            "banana",
+            "mississippi",
+            "abracadabra",
+        ] {
+            let s = s.as_bytes();
+            let (_, sa) = super::sort(s).into_parts();
+            assert_eq!(sa, naive(s), "suffix array of {:?}", s);
+        }
+
+        let mut random = Xorshift(0x9e37_79b9);
+        for len in 2..100 {
+            for alphabet in &[&b"ab"[..], b"abc", b"acgt"] {
+                let s = random.text(len, alphabet);
+                let (_, sa) = super::sort(&s).into_parts();
+                assert_eq!(sa, naive(&s), "suffix array of {:?}", s);
+            }
+        }
+    }
+
+    #[test]
+    fn divsufsort_testdata() {
+        for s in &[
+            &include_bytes!("../../divsufsort/src/testdata/fuzz1")[..],
+            &include_bytes!("../../divsufsort/src/testdata/fuzz2")[..],
+            &include_bytes!("../../divsufsort/src/testdata/fuzz3")[..],
+            &include_bytes!(
+                "../../divsufsort/src/testdata/crash-cf8673530fdca659e0ddf070b4718b9c0bb504ec"
+            )[..],
+            &include_bytes!(
+                "../../divsufsort/src/testdata/crash-ce407adf7cf638d3fa89b5637a94355d7d658872"
+            )[..],
+            &include_bytes!(
+                "../../divsufsort/src/testdata/crash-c792e788de61771b6cd65c1aa5670c62e57a33c4"
+            )[..],
+            &include_bytes!(
+                "../../divsufsort/src/testdata/crash-90b42d1c55ee90a8b004fb9db1853429ceb4c4ba"
+            )[..],
+            &include_bytes!(
+                "../../divsufsort/src/testdata/crash-8765ef2258178ca027876eab83e01d6d58db9ca0"
+            )[..],
+            &include_bytes!(
+                "../../divsufsort/src/testdata/crash-4f8c31dec8c3678a07e0fbacc6bd69e7cc9037fb"
+            )[..],
+            &include_bytes!(
+                "../../divsufsort/src/testdata/crash-16356e91966a827f79e49167170194fc3088a7ab"
+            )[..],
+        ] {
+            crosscheck(s);
+        }
+    }
+
+    #[test]
+    fn adversarial() {
+        // highly repetitive inputs, where trsort has the most work to do
+        let mut fibonacci = (b"a".to_vec(), b"ab".to_vec());
+        while fibonacci.1.len() < 100_000 {
+            let next = [&fibonacci.1[..], &fibonacci.0[..]].concat();
+            fibonacci = (fibonacci.1, next);
+        }
+        crosscheck(&fibonacci.1);
+        crosscheck(&vec![b'a'; 100_000]);
+        crosscheck(&b"ab".repeat(50_000));
+        crosscheck(&b"abaababa".repeat(10_000));
+
+        let every_byte: Vec<u8> = (0..=255).collect();
+        let s = Xorshift(0x2545_f491).text(100_000, &every_byte);
+        crosscheck(&s);
+    }
+
+    #[test]
+    fn integer_alphabet() {
+        let mut random = Xorshift(0x1234_5678);
+        for &k in &[1, 2, 3, 1000, 70_000] {
+            let s: Vec<u32> = (0..2_000).map(|_| random.next_u32() % k).collect();
+            let mut sa = vec![0; s.len()];
+            suffix_array(&s[..], &mut sa, k as usize);
+            assert_eq!(sa, naive_symbols(&s), "alphabet of size {}", k);
+        }
+
+        let s: Vec<usize> = vec![3, 1, 4, 1, 5, 9, 2, 6, 5, 3, 5];
+        let mut sa = vec![0; s.len()];
+        suffix_array(&s[..], &mut sa, 10);
+        assert_eq!(sa, naive_symbols(&s));
+    }
+}